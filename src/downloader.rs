@@ -1,6 +1,8 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
 
 #[derive(Debug, thiserror::Error)]
 pub enum DownloadError {
@@ -10,12 +12,52 @@ pub enum DownloadError {
     FfmpegNotFound,
     #[error("yt-dlp failed: {0}")]
     YtDlpFailed(String),
+    #[error("ffmpeg failed: {0}")]
+    FfmpegFailed(String),
+    #[error("no audio track found for {0}")]
+    NoAudio(String),
+    #[error("{path} is not a 16 kHz mono WAV even after reconverting it: {actual}")]
+    BadWav { path: String, actual: String },
     #[error("could not extract video ID from: {0}")]
     InvalidUrl(String),
+    #[error(
+        "ffmpeg arguments can't contain `{0}`: they are passed as separate arguments, \
+         not through a shell"
+    )]
+    InvalidFfmpegArgs(char),
+    #[error(
+        "{path} already holds audio of {}, not {video_id}; pass --force to replace it",
+        .existing.as_deref().unwrap_or("an unknown video")
+    )]
+    WouldOverwrite {
+        path: String,
+        video_id: String,
+        existing: Option<String>,
+    },
+    #[error("could not parse yt-dlp metadata: {0}")]
+    Metadata(#[from] serde_json::Error),
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
 
+/// Video metadata as reported by yt-dlp. Missing fields stay `None`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VideoMetadata {
+    /// The canonical video ID.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Duration in seconds.
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Upload date normalized to `YYYY-MM-DD` (yt-dlp reports `YYYYMMDD`).
+    #[serde(default)]
+    pub upload_date: Option<String>,
+}
+
 /// Extract the video ID from a YouTube URL or bare ID.
 pub fn extract_video_id(url: &str) -> Result<String, DownloadError> {
     // Already a bare ID (no slashes, no dots)
@@ -41,9 +83,40 @@ pub fn extract_video_id(url: &str) -> Result<String, DownloadError> {
         }
     }
 
+    // /embed/<id> (iframe players) and legacy /v/<id> links
+    for prefix in ["/embed/", "/v/"] {
+        if let Some(pos) = url.find(prefix) {
+            let id = &url[pos + prefix.len()..];
+            let id = id.split(['?', '&', '#', '/']).next().unwrap_or(id);
+            if !id.is_empty() {
+                return Ok(id.to_string());
+            }
+        }
+    }
+
     Err(DownloadError::InvalidUrl(url.to_string()))
 }
 
+/// Resolve the canonical video ID, asking yt-dlp when the string parser can't handle
+/// the URL. With `force_lookup`, yt-dlp is asked even if parsing would succeed.
+pub fn resolve_video_id(url: &str, force_lookup: bool) -> Result<String, DownloadError> {
+    if !force_lookup && let Ok(id) = extract_video_id(url) {
+        return Ok(id);
+    }
+
+    check_dependency("yt-dlp")?;
+    let output = Command::new("yt-dlp")
+        .args(["--print", "id", "--skip-download", "--no-playlist"])
+        .arg(to_full_url(url))
+        .output()?;
+
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || id.is_empty() || id.contains(char::is_whitespace) {
+        return Err(DownloadError::InvalidUrl(url.to_string()));
+    }
+    Ok(id)
+}
+
 /// Build a full YouTube URL from a URL or bare video ID.
 fn to_full_url(url: &str) -> String {
     if url.starts_with("http://") || url.starts_with("https://") {
@@ -53,6 +126,14 @@ fn to_full_url(url: &str) -> String {
     }
 }
 
+/// Link to a moment in a video: `https://www.youtube.com/watch?v=ID&t=123s`.
+pub fn deep_link(video_id: &str, seconds: f64) -> String {
+    format!(
+        "https://www.youtube.com/watch?v={video_id}&t={}s",
+        seconds.max(0.0).floor() as u64
+    )
+}
+
 fn check_dependency(name: &str) -> Result<(), DownloadError> {
     let result = Command::new("which").arg(name).output();
     match result {
@@ -73,38 +154,191 @@ fn secs_to_hms(secs: f64) -> String {
     format!("{h:02}:{m:02}:{s:02}")
 }
 
+/// Turn yt-dlp's `YYYYMMDD` into `YYYY-MM-DD`; anything else is dropped.
+fn normalize_upload_date(raw: &str) -> Option<String> {
+    if raw.len() == 8 && raw.bytes().all(|b| b.is_ascii_digit()) {
+        Some(format!("{}-{}-{}", &raw[..4], &raw[4..6], &raw[6..]))
+    } else {
+        None
+    }
+}
+
+/// Fetch title, channel, duration and upload date without downloading the media.
+pub fn fetch_metadata(url: &str) -> Result<VideoMetadata, DownloadError> {
+    check_dependency("yt-dlp")?;
+
+    let output = Command::new("yt-dlp")
+        .args(["--dump-json", "--skip-download", "--no-playlist"])
+        .arg(to_full_url(url))
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DownloadError::YtDlpFailed(stderr.into_owned()));
+    }
+
+    parse_metadata(&output.stdout)
+}
+
+/// Read metadata from a `{video_id}.info.json` sidecar written by `download`.
+/// Fields missing from the file are left `None`.
+pub fn read_info_json(path: &Path) -> Result<VideoMetadata, DownloadError> {
+    parse_metadata(&fs::read(path)?)
+}
+
+fn parse_metadata(json: &[u8]) -> Result<VideoMetadata, DownloadError> {
+    let mut meta: VideoMetadata = serde_json::from_slice(json)?;
+    meta.upload_date = meta.upload_date.as_deref().and_then(normalize_upload_date);
+    Ok(meta)
+}
+
+/// Options for `download`.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Download only `(start_secs, end_secs)`.
+    pub clip: Option<(f64, f64)>,
+    /// Also write yt-dlp's `{stem}.info.json` next to the WAV.
+    pub write_info_json: bool,
+    /// Network retries for yt-dlp (`--retries`/`--fragment-retries`), and how many
+    /// more times to rerun it if it leaves audio behind without a finished WAV.
+    /// `None` keeps yt-dlp's defaults and makes a single attempt.
+    pub retries: Option<u32>,
+    /// Keep the stream yt-dlp downloaded (`--keep-video`) instead of deleting it
+    /// after the WAV conversion, to inspect audio that transcodes badly.
+    pub keep_intermediate: bool,
+    /// Extra ffmpeg output arguments for the WAV conversion, e.g. from
+    /// `parse_ffmpeg_args("-af loudnorm")`. They follow `-ar 16000 -ac 1`, so an `-ar`
+    /// or `-ac` of their own overrides the 16 kHz mono default (and the WAV is then
+    /// left as it is rather than converted back).
+    pub ffmpeg_args: Vec<String>,
+    /// Name the files `{name}` (plus the clip bounds) instead of after the video ID,
+    /// e.g. the name a transcript is stored under. Must be a plain file name. A named
+    /// download always writes its info JSON, which records the video it came from.
+    pub name: Option<String>,
+    /// Replace an existing WAV of the same name instead of reusing it. Without it, a
+    /// WAV whose info JSON names another video, or a named one without info JSON,
+    /// fails with `DownloadError::WouldOverwrite`.
+    pub force: bool,
+}
+
+/// Characters rejected by `parse_ffmpeg_args`: shell syntax and quoting, which would
+/// reach ffmpeg literally and most likely mean the string was written for a shell.
+const FFMPEG_ARG_METACHARACTERS: &[char] =
+    &[';', '|', '&', '$', '`', '<', '>', '(', ')', '{', '}', '\'', '"', '\\', '*', '?', '!', '~'];
+
+/// Split a `--ffmpeg-args` string such as `-af highpass=f=100,loudnorm` on whitespace
+/// into separate arguments, rejecting shell metacharacters and quotes.
+pub fn parse_ffmpeg_args(args: &str) -> Result<Vec<String>, DownloadError> {
+    if let Some(c) = args.chars().find(|c| FFMPEG_ARG_METACHARACTERS.contains(c)) {
+        return Err(DownloadError::InvalidFfmpegArgs(c));
+    }
+    Ok(args.split_whitespace().map(str::to_string).collect())
+}
+
+impl DownloadOptions {
+    /// Whether `ffmpeg_args` sets its own sample rate or channel count.
+    fn overrides_format(&self) -> bool {
+        self.ffmpeg_args.iter().any(|arg| arg == "-ar" || arg == "-ac")
+    }
+}
+
+/// A downloaded WAV and where it starts in the original video.
+#[derive(Debug, Clone)]
+pub struct DownloadedAudio {
+    pub path: PathBuf,
+    /// Clip start in seconds (0 for a full download); add it to transcript timestamps.
+    pub offset_seconds: f64,
+    /// The info JSON sidecar, if one was requested and yt-dlp wrote it.
+    pub info_json: Option<PathBuf>,
+    /// The pre-conversion download, with `keep_intermediate`.
+    pub intermediate: Option<PathBuf>,
+}
+
 /// Download audio from a YouTube URL or video ID as WAV.
 ///
-/// `clip` — optional `(start_secs, end_secs)` to download only that range.
-/// Returns the path to the downloaded file.
-pub fn download(url: &str, output_dir: &str, clip: Option<(f64, f64)>) -> Result<PathBuf, DownloadError> {
+/// Partial downloads are resumed (`--continue`). If a run leaves a partial or
+/// unconverted file behind, it is retried up to `opts.retries` times; the final
+/// error carries the stderr of every attempt. A WAV that isn't 16 kHz mono is
+/// converted once more with ffmpeg before giving up with `DownloadError::BadWav`.
+/// Videos without audio (slideshows, some live replays) fail with
+/// `DownloadError::NoAudio` instead of a generic yt-dlp error.
+///
+/// An existing WAV is reused only if it holds this video's audio: whenever its info
+/// JSON is there, the ID recorded in it must match, and a named WAV (`opts.name`)
+/// without one is refused too. Such a WAV is never replaced silently.
+///
+/// Returns the downloaded file, its offset into the video and the info sidecar.
+pub fn download(
+    url: &str,
+    output_dir: &str,
+    opts: &DownloadOptions,
+) -> Result<DownloadedAudio, DownloadError> {
+    let clip = opts.clip;
     check_dependency("yt-dlp")?;
     check_dependency("ffmpeg")?;
 
-    let video_id = extract_video_id(url)?;
+    let video_id = resolve_video_id(url, false)?;
     let full_url = to_full_url(url);
     let out_path = Path::new(output_dir);
 
     fs::create_dir_all(out_path)?;
 
+    let base = opts.name.as_deref().unwrap_or(&video_id);
     let stem = match clip {
-        Some((start, end)) => format!("{video_id}_{start}_{end}"),
-        None => video_id.clone(),
+        Some((start, end)) => format!("{base}_{start}_{end}"),
+        None => base.to_string(),
     };
 
     let output_template = out_path.join(format!("{stem}.%(ext)s"));
     let wav_path = out_path.join(format!("{stem}.wav"));
+    let info_path = out_path.join(format!("{stem}.info.json"));
+    let write_info_json = opts.write_info_json || opts.name.is_some();
+
+    if wav_path.exists() {
+        if opts.force {
+            fs::remove_file(&wav_path)?;
+            let _ = fs::remove_file(&info_path);
+        } else {
+            let existing = read_info_json(&info_path).ok().and_then(|meta| meta.id);
+            // A named download always writes its info JSON, so without one the
+            // named WAV's source is unknown
+            let foreign = match &existing {
+                Some(id) => *id != video_id,
+                None => opts.name.is_some(),
+            };
+            if foreign {
+                return Err(DownloadError::WouldOverwrite {
+                    path: wav_path.display().to_string(),
+                    video_id,
+                    existing,
+                });
+            }
+        }
+    }
 
     // yt-dlp: download and convert to wav via ffmpeg postprocessor,
     // forcing 16kHz mono (required by whisper.cpp)
+    let mut postprocessor_args = String::from("ffmpeg:-ar 16000 -ac 1");
+    for arg in &opts.ffmpeg_args {
+        postprocessor_args.push(' ');
+        postprocessor_args.push_str(arg);
+    }
     let mut cmd = Command::new("yt-dlp");
-    cmd.args([
-        "--extract-audio",
-        "--audio-format",
-        "wav",
-        "--postprocessor-args",
-        "ffmpeg:-ar 16000 -ac 1",
-    ]);
+    cmd.args(["--continue", "--extract-audio", "--audio-format", "wav", "--postprocessor-args"])
+        .arg(&postprocessor_args);
+
+    if write_info_json {
+        cmd.arg("--write-info-json");
+    }
+
+    if opts.keep_intermediate {
+        cmd.arg("--keep-video");
+    }
+
+    if let Some(retries) = opts.retries {
+        let retries = retries.to_string();
+        cmd.args(["--retries", &retries, "--fragment-retries", &retries]);
+    }
 
     if let Some((start, end)) = clip {
         let section = format!("*{}-{}", secs_to_hms(start), secs_to_hms(end));
@@ -115,18 +349,203 @@ pub fn download(url: &str, output_dir: &str, clip: Option<(f64, f64)>) -> Result
         .arg(output_template.to_str().unwrap_or_default())
         .arg(&full_url);
 
-    let output = cmd.output()?;
+    let attempts = opts.retries.unwrap_or(0) + 1;
+    let mut stderr_log = String::new();
+    for attempt in 1..=attempts {
+        let output = cmd.output()?;
+
+        if output.status.success() && wav_path.exists() {
+            if is_empty_wav(&wav_path) {
+                let _ = fs::remove_file(&wav_path);
+                return Err(DownloadError::NoAudio(video_id));
+            }
+            if !opts.overrides_format() {
+                ensure_whisper_wav(&wav_path)?;
+            }
+            return Ok(DownloadedAudio {
+                path: wav_path,
+                offset_seconds: clip.map_or(0.0, |(start, _)| start),
+                info_json: (write_info_json && info_path.exists()).then_some(info_path),
+                intermediate: opts
+                    .keep_intermediate
+                    .then(|| non_wav_files(out_path, &stem).into_iter().next())
+                    .flatten(),
+            });
+        }
 
-    if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        // Retrying wouldn't give a video without an audio stream one
+        if reports_no_audio(&stderr) {
+            return Err(DownloadError::NoAudio(video_id));
+        }
+        let stderr = if output.status.success() {
+            "download succeeded but WAV file not found".into()
+        } else {
+            stderr
+        };
+        if attempts > 1 {
+            stderr_log.push_str(&format!("attempt {attempt}/{attempts}: "));
+        }
+        stderr_log.push_str(stderr.trim_end());
+        stderr_log.push('\n');
+
+        // Only a leftover partial is worth another run; other failures would repeat
+        if non_wav_files(out_path, &stem).is_empty() {
+            break;
+        }
+    }
+
+    Err(DownloadError::YtDlpFailed(stderr_log))
+}
+
+/// Stream audio to stdout as a 16 kHz mono WAV instead of saving it: yt-dlp writes
+/// the best audio stream to a pipe and ffmpeg converts it on the fly. Nothing is
+/// written to disk, so there is no path to return, and `write_info_json` is ignored.
+/// A clip is cut by ffmpeg, after the whole stream has been fetched.
+pub fn download_to_stdout(url: &str, opts: &DownloadOptions) -> Result<(), DownloadError> {
+    check_dependency("yt-dlp")?;
+    check_dependency("ffmpeg")?;
+
+    let mut ytdlp = Command::new("yt-dlp");
+    ytdlp.args([
+        "--format",
+        "bestaudio/best",
+        "--no-playlist",
+        "--quiet",
+        "--no-progress",
+        "--output",
+        "-",
+    ]);
+    if let Some(retries) = opts.retries {
+        let retries = retries.to_string();
+        ytdlp.args(["--retries", &retries, "--fragment-retries", &retries]);
+    }
+    let mut ytdlp = ytdlp
+        .arg(to_full_url(url))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let audio = ytdlp.stdout.take().expect("yt-dlp stdout is piped");
+
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg.args(["-hide_banner", "-loglevel", "error", "-i", "pipe:0"]);
+    if let Some((start, end)) = opts.clip {
+        ffmpeg.args(["-ss", &start.to_string(), "-to", &end.to_string()]);
+    }
+    let converted = ffmpeg
+        .args(["-ar", "16000", "-ac", "1"])
+        .args(&opts.ffmpeg_args)
+        .args(["-f", "wav", "pipe:1"])
+        .stdin(audio)
+        .stdout(Stdio::inherit())
+        .output()?;
+    let fetched = ytdlp.wait_with_output()?;
+
+    if !fetched.status.success() {
+        let stderr = String::from_utf8_lossy(&fetched.stderr);
         return Err(DownloadError::YtDlpFailed(stderr.into_owned()));
     }
+    if !converted.status.success() {
+        let stderr = String::from_utf8_lossy(&converted.stderr);
+        return Err(DownloadError::FfmpegFailed(stderr.into_owned()));
+    }
+    Ok(())
+}
 
-    if wav_path.exists() {
-        Ok(wav_path)
-    } else {
-        Err(DownloadError::YtDlpFailed(
-            "download succeeded but WAV file not found".into(),
-        ))
+/// Whether the WAV at `path` holds no samples (an empty file, or only a header).
+fn is_empty_wav(path: &Path) -> bool {
+    if fs::metadata(path).is_ok_and(|m| m.len() == 0) {
+        return true;
+    }
+    hound::WavReader::open(path).is_ok_and(|reader| reader.duration() == 0)
+}
+
+/// Whether yt-dlp's stderr says the video had no audio stream to extract.
+fn reports_no_audio(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    ["does not contain any stream", "unable to obtain file audio codec"]
+        .iter()
+        .any(|needle| stderr.contains(needle))
+}
+
+/// Make sure `path` is 16 kHz mono, as whisper expects. ffmpeg sometimes ignores
+/// yt-dlp's postprocessor arguments, so a wrong WAV is converted once more here.
+fn ensure_whisper_wav(path: &Path) -> Result<(), DownloadError> {
+    if wav_spec_problem(path).is_none() {
+        return Ok(());
+    }
+
+    let converted = path.with_extension("16k.wav");
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(path)
+        .args(["-ar", "16000", "-ac", "1"])
+        .arg(&converted)
+        .output()?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&converted);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DownloadError::FfmpegFailed(stderr.into_owned()));
+    }
+    fs::rename(&converted, path)?;
+
+    match wav_spec_problem(path) {
+        None => Ok(()),
+        Some(actual) => Err(DownloadError::BadWav {
+            path: path.display().to_string(),
+            actual,
+        }),
+    }
+}
+
+/// What is wrong with `path` as whisper input, or `None` if it is 16 kHz mono.
+fn wav_spec_problem(path: &Path) -> Option<String> {
+    match hound::WavReader::open(path) {
+        Ok(reader) => {
+            let spec = reader.spec();
+            (spec.sample_rate != 16000 || spec.channels != 1)
+                .then(|| format!("{} Hz, {} channel(s)", spec.sample_rate, spec.channels))
+        }
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+/// `{stem}.*` files in `dir` other than the WAV and info sidecar: the original
+/// stream yt-dlp downloaded, possibly partial or not yet converted.
+fn non_wav_files(dir: &Path, stem: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let prefix = format!("{stem}.");
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix(&prefix)
+                .is_some_and(|ext| ext != "wav" && ext != "info.json")
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_embed_and_legacy_ids() {
+        let cases = [
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ", "dQw4w9WgXcQ"),
+            ("https://www.youtube.com/embed/dQw4w9WgXcQ?start=42", "dQw4w9WgXcQ"),
+            ("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ#t=1", "dQw4w9WgXcQ"),
+            ("https://www.youtube.com/v/dQw4w9WgXcQ", "dQw4w9WgXcQ"),
+            ("https://www.youtube.com/v/dQw4w9WgXcQ&hl=en", "dQw4w9WgXcQ"),
+            ("youtube.com/v/dQw4w9WgXcQ/", "dQw4w9WgXcQ"),
+        ];
+        for (url, id) in cases {
+            assert_eq!(extract_video_id(url).unwrap(), id, "{url}");
+        }
+        assert!(extract_video_id("https://www.youtube.com/embed/").is_err());
     }
 }
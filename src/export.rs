@@ -1,14 +1,70 @@
+use std::borrow::Cow;
+use std::io::Write;
 use std::path::Path;
 
+use serde::Serialize;
+
+use crate::time::{format_clock, format_timecode};
+
 #[derive(Debug, thiserror::Error)]
 pub enum ExportError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error("csv error: {0}")]
     Csv(#[from] csv::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{path}: {message}")]
+    InvalidCsv { path: String, message: String },
+    #[error(transparent)]
+    Import(#[from] crate::import::ImportError),
+}
+
+/// Output formats shared by every command that prints segments or search hits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Csv,
+    Json,
+    Jsonl,
+    Srt,
+    Vtt,
+    Txt,
+    Md,
+}
+
+impl OutputFormat {
+    /// File extension for files written in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Table | OutputFormat::Txt => "txt",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Json => "json",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+            OutputFormat::Md => "md",
+        }
+    }
+
+    /// Guess the format from a file name's extension (`.txt` maps to plain text).
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "csv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" | "ndjson" => Some(OutputFormat::Jsonl),
+            "srt" => Some(OutputFormat::Srt),
+            "vtt" => Some(OutputFormat::Vtt),
+            "txt" => Some(OutputFormat::Txt),
+            "md" => Some(OutputFormat::Md),
+            _ => None,
+        }
+    }
 }
 
 /// A segment to export — module-independent, no imports from store.
+#[derive(Debug, Clone, Serialize)]
 pub struct ExportSegment {
     pub index: usize,
     pub start: f64,
@@ -16,6 +72,64 @@ pub struct ExportSegment {
     pub text: String,
 }
 
+/// A search hit to export, with a deep link to its moment in the video.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportHit {
+    pub rank: usize,
+    /// Store the hit came from, when several were searched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<String>,
+    pub video_id: String,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub distance: f32,
+    pub confidence: Option<f32>,
+    pub url: String,
+}
+
+/// A list of segments plus where they came from.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SegmentList<'a> {
+    /// Video the segments belong to (stored transcripts).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_id: Option<&'a str>,
+    /// Input file (transcripts that never went through the store).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<&'a str>,
+    pub language: Option<&'a str>,
+    pub segments: &'a [ExportSegment],
+}
+
+/// UTF-8 byte order mark. Excel needs it to open UTF-8 CSV without mojibake.
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+/// Columns of a CSV that `append_csv` accumulates several videos in.
+const APPEND_CSV_HEADER: [&str; 4] = ["video_id", "start", "end", "text"];
+
+/// Presentation settings for `render`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderOptions {
+    /// Wrap tables to this many columns.
+    pub table_width: Option<u16>,
+    /// Start CSV output with a UTF-8 BOM (ignored for other formats).
+    pub bom: bool,
+    /// Indent JSON documents instead of writing them on one line (JSONL stays compact).
+    pub pretty_json: bool,
+    /// Cut table Text cells to this many characters, ending in `…`. Other formats
+    /// always carry the full text.
+    pub max_table_text: Option<usize>,
+    /// Have `render_to` add CSV segment rows to the file with `append_csv` instead of
+    /// replacing it.
+    pub append: bool,
+}
+
+/// What `render` can print.
+#[derive(Debug, Clone, Copy)]
+pub enum Rows<'a> {
+    Segments(SegmentList<'a>),
+    Hits(&'a [ExportHit]),
+}
+
 /// Format segments as a console table.
 pub fn format_table(video_id: &str, segments: &[ExportSegment]) -> comfy_table::Table {
     let mut table = comfy_table::Table::new();
@@ -25,8 +139,8 @@ pub fn format_table(video_id: &str, segments: &[ExportSegment]) -> comfy_table::
         table.add_row([
             (seg.index + 1).to_string(),
             video_id.to_string(),
-            format_timestamp(seg.start),
-            format_timestamp(seg.end),
+            format_clock(seg.start),
+            format_clock(seg.end),
             seg.text.clone(),
         ]);
     }
@@ -41,8 +155,8 @@ pub fn write_csv(path: &str, segments: &[ExportSegment]) -> Result<(), ExportErr
 
     for seg in segments {
         wtr.write_record([
-            &format_timestamp(seg.start),
-            &format_timestamp(seg.end),
+            &format_clock(seg.start),
+            &format_clock(seg.end),
             &seg.text,
         ])?;
     }
@@ -51,6 +165,64 @@ pub fn write_csv(path: &str, segments: &[ExportSegment]) -> Result<(), ExportErr
     Ok(())
 }
 
+/// Add a video's segments to a CSV file that collects several videos, as
+/// `video_id, start, end, text` rows. The header is only written when the file is new
+/// or empty; an existing file with other columns is rejected rather than mixed into.
+pub fn append_csv(
+    path: &str,
+    video_id: &str,
+    segments: &[ExportSegment],
+    bom: bool,
+) -> Result<(), ExportError> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).read(true).open(path)?;
+    let fresh = file.metadata()?.len() == 0;
+    if fresh {
+        if bom {
+            file.write_all(UTF8_BOM)?;
+        }
+    } else {
+        let mut first_line = String::new();
+        std::io::BufRead::read_line(&mut std::io::BufReader::new(&file), &mut first_line)?;
+        let columns = first_line.trim_start_matches('\u{feff}').trim_end();
+        if columns != APPEND_CSV_HEADER.join(",") {
+            return Err(ExportError::InvalidCsv {
+                path: path.to_string(),
+                message: format!(
+                    "can't append, its columns are `{columns}` instead of `{}`",
+                    APPEND_CSV_HEADER.join(",")
+                ),
+            });
+        }
+    }
+
+    let mut wtr = csv::Writer::from_writer(std::io::BufWriter::new(file));
+    if fresh {
+        wtr.write_record(APPEND_CSV_HEADER)?;
+    }
+    for seg in segments {
+        wtr.write_record([video_id, &format_clock(seg.start), &format_clock(seg.end), &seg.text])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Read segments back from a CSV file written by `write_csv` (or `--format csv`), e.g.
+/// after editing it in a spreadsheet. Rows are streamed, not loaded at once.
+///
+/// Parsed like a CSV given to `import::read_transcript`: `start`, `end` and `text`
+/// columns in any order, extra columns ignored, times as clock times (`MM:SS.ss`,
+/// `H:MM:SS.ss`) or plain seconds, text trimmed. Segments are indexed in file order,
+/// and a leading UTF-8 BOM is skipped.
+pub fn read_csv(path: &str) -> Result<Vec<ExportSegment>, ExportError> {
+    let file = std::fs::File::open(path)?;
+    let segments = crate::import::parse_csv(path, file)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, seg)| ExportSegment { index, start: seg.start, end: seg.end, text: seg.text })
+        .collect();
+    Ok(segments)
+}
+
 /// Write segments as CSV to stdout.
 pub fn write_csv_stdout(segments: &[ExportSegment]) -> Result<(), ExportError> {
     let stdout = std::io::stdout();
@@ -59,8 +231,8 @@ pub fn write_csv_stdout(segments: &[ExportSegment]) -> Result<(), ExportError> {
 
     for seg in segments {
         wtr.write_record([
-            &format_timestamp(seg.start),
-            &format_timestamp(seg.end),
+            &format_clock(seg.start),
+            &format_clock(seg.end),
             &seg.text,
         ])?;
     }
@@ -69,8 +241,362 @@ pub fn write_csv_stdout(segments: &[ExportSegment]) -> Result<(), ExportError> {
     Ok(())
 }
 
-fn format_timestamp(seconds: f64) -> String {
-    let mins = (seconds / 60.0) as u32;
-    let secs = seconds % 60.0;
-    format!("{mins:02}:{secs:05.2}")
+/// One JSON Lines record: the segment plus the video it belongs to.
+#[derive(Serialize)]
+struct JsonlRecord<'a> {
+    video_id: &'a str,
+    #[serde(flatten)]
+    segment: &'a ExportSegment,
+}
+
+/// Write segments as JSON Lines: one `{video_id, index, start, end, text}` object per line.
+/// Times are plain seconds so the output is easy to post-process (e.g. with `jq -c`).
+pub fn write_jsonl<W: Write>(
+    mut writer: W,
+    video_id: &str,
+    segments: &[ExportSegment],
+) -> Result<(), ExportError> {
+    for segment in segments {
+        serde_json::to_writer(&mut writer, &JsonlRecord { video_id, segment })?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write search hits as CSV (`rank, video_id, start, end, text, distance, url`).
+pub fn write_hits_csv<W: Write>(writer: W, hits: &[ExportHit]) -> Result<(), ExportError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(["rank", "video_id", "start", "end", "text", "distance", "url"])?;
+
+    for hit in hits {
+        wtr.write_record([
+            &hit.rank.to_string(),
+            &hit.video_id,
+            &format_clock(hit.start),
+            &format_clock(hit.end),
+            &hit.text,
+            &format!("{:.4}", hit.distance),
+            &hit.url,
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Write search hits as a pretty-printed JSON array; times are plain seconds.
+pub fn write_hits_json<W: Write>(mut writer: W, hits: &[ExportHit]) -> Result<(), ExportError> {
+    serde_json::to_writer_pretty(&mut writer, hits)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write `value` as one JSON document and a newline, indented if `pretty`.
+fn write_json<W: Write, T: Serialize>(
+    mut writer: W,
+    value: &T,
+    pretty: bool,
+) -> Result<(), ExportError> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, value)?;
+    } else {
+        serde_json::to_writer(&mut writer, value)?;
+    }
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Render rows in `format`. This is the one place output formats are implemented.
+pub fn render<W: Write>(
+    mut writer: W,
+    format: OutputFormat,
+    rows: Rows,
+    opts: RenderOptions,
+) -> Result<(), ExportError> {
+    if opts.bom && format == OutputFormat::Csv {
+        writer.write_all(UTF8_BOM)?;
+    }
+
+    match (format, rows) {
+        (OutputFormat::Table, rows) => {
+            let mut table = match rows {
+                Rows::Segments(list) => segments_table(&list, opts.max_table_text),
+                Rows::Hits(hits) => hits_table(hits, opts.max_table_text),
+            };
+            if let Some(width) = opts.table_width {
+                table
+                    .set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+                    .set_width(width);
+            }
+            writeln!(writer, "{table}")?;
+        }
+        (OutputFormat::Csv, Rows::Segments(list)) => {
+            let mut wtr = csv::Writer::from_writer(&mut writer);
+            wtr.write_record(["start", "end", "text"])?;
+            for seg in list.segments {
+                wtr.write_record([&format_clock(seg.start), &format_clock(seg.end), &seg.text])?;
+            }
+            wtr.flush()?;
+        }
+        (OutputFormat::Csv, Rows::Hits(hits)) => write_hits_csv(&mut writer, hits)?,
+        (OutputFormat::Json, Rows::Segments(list)) => {
+            #[derive(Serialize)]
+            struct Document<'a> {
+                #[serde(flatten)]
+                list: &'a SegmentList<'a>,
+                no_speech: bool,
+            }
+            let no_speech = list.segments.iter().all(|s| s.text.trim().is_empty());
+            let doc = Document { list: &list, no_speech };
+            write_json(&mut writer, &doc, opts.pretty_json)?;
+        }
+        (OutputFormat::Json, Rows::Hits(hits)) => write_json(&mut writer, &hits, opts.pretty_json)?,
+        (OutputFormat::Jsonl, Rows::Segments(list)) => {
+            write_jsonl(&mut writer, list.video_id.or(list.file).unwrap_or_default(), list.segments)?
+        }
+        (OutputFormat::Jsonl, Rows::Hits(hits)) => {
+            for hit in hits {
+                serde_json::to_writer(&mut writer, hit)?;
+                writeln!(writer)?;
+            }
+        }
+        (OutputFormat::Srt | OutputFormat::Vtt, rows) => {
+            let vtt = format == OutputFormat::Vtt;
+            if vtt {
+                writeln!(writer, "WEBVTT\n")?;
+            }
+            for (i, (start, end, text)) in cues(&rows).into_iter().enumerate() {
+                let sep = if vtt { '.' } else { ',' };
+                if !vtt {
+                    writeln!(writer, "{}", i + 1)?;
+                }
+                writeln!(
+                    writer,
+                    "{} --> {}\n{text}\n",
+                    format_timecode(start, sep),
+                    format_timecode(end, sep)
+                )?;
+            }
+        }
+        (OutputFormat::Txt, Rows::Segments(list)) => {
+            for seg in list.segments {
+                writeln!(writer, "{}", seg.text)?;
+            }
+        }
+        (OutputFormat::Txt, Rows::Hits(hits)) => {
+            for hit in hits {
+                writeln!(
+                    writer,
+                    "{}. [{} {}] {}\n   {}",
+                    hit.rank,
+                    hit.video_id,
+                    format_clock(hit.start),
+                    hit.text,
+                    hit.url
+                )?;
+            }
+        }
+        (OutputFormat::Md, Rows::Segments(list)) => {
+            writeln!(writer, "| Start | End | Text |\n|---|---|---|")?;
+            for seg in list.segments {
+                writeln!(
+                    writer,
+                    "| {} | {} | {} |",
+                    format_clock(seg.start),
+                    format_clock(seg.end),
+                    md_escape(&seg.text)
+                )?;
+            }
+        }
+        (OutputFormat::Md, Rows::Hits(hits)) => {
+            writeln!(writer, "| # | Video | Time | Text | Distance |\n|---|---|---|---|---|")?;
+            for hit in hits {
+                writeln!(
+                    writer,
+                    "| {} | [{}]({}) | {} | {} | {:.4} |",
+                    hit.rank,
+                    hit.video_id,
+                    hit.url,
+                    format_clock(hit.start),
+                    md_escape(&hit.text),
+                    hit.distance
+                )?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Render to `path`, or to stdout when it is `-`. With `opts.append`, CSV segment
+/// rows go through `append_csv`.
+pub fn render_to(
+    path: &str,
+    format: OutputFormat,
+    rows: Rows,
+    opts: RenderOptions,
+) -> Result<(), ExportError> {
+    if let (true, OutputFormat::Csv, Rows::Segments(list)) = (opts.append, format, rows)
+        && path != "-"
+    {
+        let video_id = list.video_id.or(list.file).unwrap_or_default();
+        append_csv(path, video_id, list.segments, opts.bom)
+    } else if path == "-" {
+        render(std::io::stdout().lock(), format, rows, opts)
+    } else {
+        let file = std::fs::File::create(path)?;
+        render(std::io::BufWriter::new(file), format, rows, opts)
+    }
+}
+
+fn segments_table(list: &SegmentList, max_text: Option<usize>) -> comfy_table::Table {
+    let segments: Cow<[ExportSegment]> = match max_text {
+        Some(max) => list
+            .segments
+            .iter()
+            .map(|s| ExportSegment { text: shorten(&s.text, Some(max)), ..s.clone() })
+            .collect(),
+        None => Cow::Borrowed(list.segments),
+    };
+    match list.video_id {
+        Some(video_id) => format_table(video_id, &segments),
+        None => {
+            let mut table = comfy_table::Table::new();
+            table.set_header(["#", "Start", "End", "Text"]);
+            for (i, seg) in segments.iter().enumerate() {
+                table.add_row([
+                    (i + 1).to_string(),
+                    format_clock(seg.start),
+                    format_clock(seg.end),
+                    seg.text.clone(),
+                ]);
+            }
+            table
+        }
+    }
+}
+
+fn hits_table(hits: &[ExportHit], max_text: Option<usize>) -> comfy_table::Table {
+    let with_store = hits.iter().any(|h| h.store.is_some());
+    let mut header = vec!["#", "Video", "Time", "Text", "Distance", "Conf"];
+    if with_store {
+        header.insert(1, "Store");
+    }
+
+    let mut table = comfy_table::Table::new();
+    table.set_header(header);
+    for hit in hits {
+        let mut row = vec![
+            hit.rank.to_string(),
+            hit.video_id.clone(),
+            format!("{}-{}", format_clock(hit.start), format_clock(hit.end)),
+            shorten(&hit.text, max_text),
+            format!("{:.4}", hit.distance),
+            hit.confidence
+                .map(|c| format!("{c:.2}"))
+                .unwrap_or_else(|| "-".into()),
+        ];
+        if with_store {
+            row.insert(1, hit.store.clone().unwrap_or_default());
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+/// Subtitle cues; search hits are prefixed with their video since they mix sources.
+fn cues(rows: &Rows) -> Vec<(f64, f64, String)> {
+    match rows {
+        Rows::Segments(list) => list
+            .segments
+            .iter()
+            .map(|s| (s.start, s.end, s.text.clone()))
+            .collect(),
+        Rows::Hits(hits) => hits
+            .iter()
+            .map(|h| (h.start, h.end, format!("[{}] {}", h.video_id, h.text)))
+            .collect(),
+    }
+}
+
+/// `text` cut to `max_chars` characters plus `…`, or unchanged if it fits. ANSI
+/// escape sequences (search highlighting) aren't counted and never split; a cut
+/// inside a highlight resets the colour after the `…`.
+fn shorten(text: &str, max_chars: Option<usize>) -> String {
+    let Some(max) = max_chars else {
+        return text.to_string();
+    };
+    let mut kept = String::with_capacity(text.len());
+    let mut visible = 0;
+    let mut styled = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ parameters, ended by a byte in @..~
+            let start = kept.len();
+            kept.push(c);
+            kept.extend(chars.next_if_eq(&'['));
+            for c in chars.by_ref() {
+                kept.push(c);
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+            styled = &kept[start..] != "\x1b[0m";
+            continue;
+        }
+        if visible == max {
+            let mut out = format!("{}…", kept.trim_end());
+            if styled {
+                out.push_str("\x1b[0m");
+            }
+            return out;
+        }
+        kept.push(c);
+        visible += 1;
+    }
+    kept
+}
+
+fn md_escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_round_trip() {
+        let segments: Vec<ExportSegment> = [
+            (0.0, 1.5, "Hello, world"),
+            (62.25, 65.0, "She said \"hi\""),
+            (3725.75, 3730.1, "an hour in"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (start, end, text))| ExportSegment {
+            index,
+            start,
+            end,
+            text: text.to_string(),
+        })
+        .collect();
+        let file = format!("sawtrs-roundtrip-{}.csv", std::process::id());
+        let path = std::env::temp_dir().join(file);
+        let path = path.to_str().unwrap();
+
+        write_csv(path, &segments).unwrap();
+        let read = read_csv(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let fields = |segs: &[ExportSegment]| -> Vec<(usize, f64, f64, String)> {
+            segs.iter().map(|s| (s.index, s.start, s.end, s.text.clone())).collect()
+        };
+        assert_eq!(fields(&read), fields(&segments));
+    }
 }
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::time::parse_clock;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("csv error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("can't tell the transcript format of {0}, pass --format")]
+    UnknownFormat(String),
+    #[error("{path}:{line}: {message}")]
+    Malformed {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    #[error("csv file {0} has no `{1}` column")]
+    MissingColumn(String, &'static str),
+    #[error("no segments found in {0}")]
+    NoSegments(String),
+}
+
+/// Transcript formats `read_transcript` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormat {
+    Srt,
+    Vtt,
+    Csv,
+    Json,
+}
+
+impl ImportFormat {
+    /// Guess the format from a file name's extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "srt" => Some(ImportFormat::Srt),
+            "vtt" => Some(ImportFormat::Vtt),
+            "csv" => Some(ImportFormat::Csv),
+            "json" => Some(ImportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// One timed line of text read from a transcript file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ImportedSegment {
+    /// Seconds from the start of the recording.
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Read a transcript written by sawtrs or another tool. `format` defaults to the
+/// file's extension. Cues with blank text are dropped.
+///
+/// - SRT/VTT: cue numbers, VTT headers, notes and cue settings are ignored, and the
+///   lines of a cue are joined with spaces. VTT markup such as `<v Name>` is removed.
+/// - CSV: a header row with `start`, `end` and `text` columns (in any order, extra
+///   columns ignored); times as clock times (`01:02.50`) or plain seconds.
+/// - JSON: `sawtrs export --format json` output (an object with `segments`), or a
+///   bare array of `{start, end, text}` objects with times in seconds.
+pub fn read_transcript(
+    path: &str,
+    format: Option<ImportFormat>,
+) -> Result<Vec<ImportedSegment>, ImportError> {
+    let format = format
+        .or_else(|| ImportFormat::from_path(path))
+        .ok_or_else(|| ImportError::UnknownFormat(path.to_string()))?;
+    let data = std::fs::read_to_string(path)?;
+    let data = data.strip_prefix('\u{feff}').unwrap_or(&data);
+
+    let segments = match format {
+        ImportFormat::Srt | ImportFormat::Vtt => parse_cues(path, data)?,
+        ImportFormat::Csv => parse_csv(path, data.as_bytes())?,
+        ImportFormat::Json => parse_json(data)?,
+    };
+    let segments: Vec<ImportedSegment> =
+        segments.into_iter().filter(|s| !s.text.trim().is_empty()).collect();
+
+    if segments.is_empty() {
+        return Err(ImportError::NoSegments(path.to_string()));
+    }
+    Ok(segments)
+}
+
+/// Parse SRT or WebVTT: every block with a `start --> end` line is a cue, the lines
+/// after it up to the next blank line are its text.
+fn parse_cues(path: &str, data: &str) -> Result<Vec<ImportedSegment>, ImportError> {
+    let mut segments = Vec::new();
+    let mut lines = data.lines().enumerate().peekable();
+
+    while let Some((i, line)) = lines.next() {
+        let Some((start, rest)) = line.split_once("-->") else {
+            continue;
+        };
+        // VTT cue settings (`align:start` ...) follow the end time
+        let end = rest.split_whitespace().next().unwrap_or_default();
+        let malformed = |message: String| ImportError::Malformed {
+            path: path.to_string(),
+            line: i + 1,
+            message,
+        };
+        let start = parse_timecode(start)
+            .ok_or_else(|| malformed(format!("bad start time `{}`", start.trim())))?;
+        let end = parse_timecode(end).ok_or_else(|| malformed(format!("bad end time `{end}`")))?;
+
+        let mut text = Vec::new();
+        while let Some((_, line)) = lines.next_if(|(_, l)| !l.trim().is_empty()) {
+            text.push(strip_tags(line.trim()));
+        }
+        segments.push(ImportedSegment { start, end, text: text.join(" ") });
+    }
+
+    Ok(segments)
+}
+
+/// `HH:MM:SS,mmm` (SRT), `HH:MM:SS.mmm` or `MM:SS.mmm` (VTT) in seconds.
+fn parse_timecode(s: &str) -> Option<f64> {
+    parse_clock(&s.trim().replace(',', "."))
+}
+
+/// Drop `<...>` markup (voice spans, styling, karaoke timestamps) from a VTT line.
+fn strip_tags(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+/// Read CSV rows from `reader` (named `path` in errors) as they stream in. Header
+/// names may carry a UTF-8 BOM.
+pub(crate) fn parse_csv(
+    path: &str,
+    reader: impl std::io::Read,
+) -> Result<Vec<ImportedSegment>, ImportError> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let headers = rdr.headers()?.clone();
+    let column = |name: &'static str| {
+        headers
+            .iter()
+            .position(|h| h.trim_start_matches('\u{feff}').trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| ImportError::MissingColumn(path.to_string(), name))
+    };
+    let (start_col, end_col, text_col) = (column("start")?, column("end")?, column("text")?);
+
+    let mut segments = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let time = |col: usize, name: &str| {
+            let value = record.get(col).unwrap_or_default();
+            parse_clock(value).ok_or_else(|| ImportError::Malformed {
+                path: path.to_string(),
+                line,
+                message: format!("bad {name} time `{value}`"),
+            })
+        };
+        segments.push(ImportedSegment {
+            start: time(start_col, "start")?,
+            end: time(end_col, "end")?,
+            text: record.get(text_col).unwrap_or_default().trim().to_string(),
+        });
+    }
+
+    Ok(segments)
+}
+
+fn parse_json(data: &str) -> Result<Vec<ImportedSegment>, ImportError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Document {
+        List { segments: Vec<ImportedSegment> },
+        Bare(Vec<ImportedSegment>),
+    }
+
+    Ok(match serde_json::from_str(data)? {
+        Document::List { segments } | Document::Bare(segments) => segments,
+    })
+}
@@ -0,0 +1,13 @@
+//! Download, transcribe, search and export YouTube audio.
+//!
+//! Each module stands on its own; `pipeline` wires them together for callers that
+//! want the whole download → transcribe → store flow.
+
+pub mod downloader;
+pub mod export;
+pub mod import;
+pub mod pipeline;
+pub mod rerank;
+pub mod store;
+pub mod time;
+pub mod transcriber;
@@ -1,70 +1,367 @@
-mod downloader;
-#[allow(dead_code)]
-mod export;
-#[allow(dead_code)]
-mod store;
-mod transcriber;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
+use sawtrs::export::OutputFormat;
+use sawtrs::time::{format_clock, parse_clock};
+use sawtrs::rerank::Reranker;
+use sawtrs::{downloader, export, import, pipeline, rerank, store, transcriber};
 
-const STORE_DIR: &str = "store_data";
+const STORE_DIR: &str = pipeline::DEFAULT_STORE_DIR;
+const DOWNLOADS_DIR: &str = pipeline::DEFAULT_DOWNLOADS_DIR;
+/// Default `--truncate` for the Text column of search and export tables.
+const DEFAULT_TRUNCATE: usize = 120;
 
 #[derive(Parser)]
-#[command(name = "sawtrs", about = "Download, transcribe, search and export YouTube audio")]
+#[command(
+    name = "sawtrs",
+    version,
+    about = "Download, transcribe, search and export YouTube audio"
+)]
 struct Cli {
     /// debug logging
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Disable colored output (also honoured: the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Directory to look for whisper models in (also: SAWT_MODEL_DIR) [default: models]
+    #[arg(long, global = true, value_name = "DIR")]
+    model_dir: Option<String>,
+
+    /// How long Ollama keeps the embedding model loaded after a request (e.g. 10m, 1h, -1m = forever)
+    #[arg(long, global = true, value_name = "DURATION")]
+    ollama_keepalive: Option<String>,
+
+    /// Indent JSON output for reading
+    #[arg(long, global = true, conflicts_with = "compact")]
+    pretty: bool,
+
+    /// Write JSON output on one line, for piping (the default)
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Ollama model to embed with [default: nomic-embed-text]; must match the store's
+    #[arg(long, global = true, value_name = "MODEL")]
+    embed_model: Option<String>,
+
+    /// Don't write store changes to disk; they only last for this run (for experiments)
+    #[arg(long, global = true)]
+    no_persist: bool,
+
+    /// Read defaults for these global options from a TOML file; flags still win
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<String>,
+
     #[command(subcommand)]
     command: Command,
 }
 
+/// Defaults for the global options, from `--config FILE`: a TOML table with keys named
+/// like the flags (`model_dir` or `model-dir`), strings for the valued options and
+/// booleans for the switches. Unknown keys are an error.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    verbose: Option<bool>,
+    #[serde(alias = "no-color")]
+    no_color: Option<bool>,
+    pretty: Option<bool>,
+    #[serde(alias = "model-dir")]
+    model_dir: Option<String>,
+    #[serde(alias = "ollama-keepalive")]
+    ollama_keepalive: Option<String>,
+    #[serde(alias = "embed-model")]
+    embed_model: Option<String>,
+}
+
+impl ConfigFile {
+    fn read(path: &str) -> Result<Self, String> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!("config file {path} does not exist"));
+            }
+            Err(e) => return Err(format!("{path}: {e}")),
+        };
+
+        toml::from_str(&data).map_err(|e| format!("{path}: {}", e.to_string().trim_end()))
+    }
+
+    /// Fill in whatever wasn't given on the command line.
+    fn apply(self, cli: &mut Cli) {
+        cli.verbose |= self.verbose.unwrap_or(false);
+        cli.no_color |= self.no_color.unwrap_or(false);
+        cli.pretty |= !cli.compact && self.pretty.unwrap_or(false);
+        cli.model_dir = cli.model_dir.take().or(self.model_dir);
+        cli.ollama_keepalive = cli.ollama_keepalive.take().or(self.ollama_keepalive);
+        cli.embed_model = cli.embed_model.take().or(self.embed_model);
+    }
+}
+
+/// Order of segments in `export` output.
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ExportSort {
+    /// Chronological
+    Start,
+    /// Longest first
+    Duration,
+    /// Alphabetical, case-insensitive
+    Text,
+}
+
+/// `export --sort`, then `--reverse` on top.
+#[derive(Clone, Copy)]
+struct ExportOrder {
+    sort: ExportSort,
+    reverse: bool,
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// Download audio from YouTube
     Download {
         /// YouTube URL or video ID
         url: String,
-        /// Output directory
-        #[arg(short, long, default_value = "downloads")]
+        /// Output directory, or `-` to stream the WAV to stdout
+        #[arg(short, long, default_value = DOWNLOADS_DIR)]
         output: String,
-        /// Clip start time in seconds (e.g. 30 or 90.5)
-        #[arg(long)]
+        /// Clip start time in seconds or as a clock time (e.g. 90.5, 1:30 or 1:02:03)
+        #[arg(long, value_parser = parse_clock_arg)]
         start: Option<f64>,
-        /// Clip end time in seconds (e.g. 120 or 300.0)
-        #[arg(long)]
+        /// Clip end time in seconds or as a clock time (e.g. 300, 5:00 or 1:05:00)
+        #[arg(long, value_parser = parse_clock_arg)]
         end: Option<f64>,
+        /// Ask yt-dlp for the canonical video ID instead of parsing the URL
+        #[arg(long)]
+        resolve_id: bool,
+        /// Also save yt-dlp's metadata as `{stem}.info.json` next to the WAV
+        #[arg(long)]
+        write_info_json: bool,
+        /// Retry flaky downloads: passed to yt-dlp, and reruns it if conversion is left unfinished
+        #[arg(long, value_name = "N")]
+        retries: Option<u32>,
+        /// Keep the original downloaded stream next to the WAV, for debugging conversions
+        #[arg(long)]
+        keep_intermediate: bool,
+        /// Extra ffmpeg arguments for the conversion, e.g. "-af loudnorm" (no shell syntax)
+        #[arg(long, value_name = "ARGS", allow_hyphen_values = true)]
+        ffmpeg_args: Option<String>,
+        /// Save the audio as NAME.wav instead of after the video ID, like `pipeline --as`
+        #[arg(long = "as", value_name = "NAME", value_parser = parse_store_name)]
+        name: Option<String>,
+        /// Download again, replacing an existing WAV of the same name even if it holds
+        /// another video's audio
+        #[arg(long)]
+        force: bool,
     },
 
-    /// Transcribe a WAV file
+    /// Transcribe one or more WAV files or YouTube URLs (nothing is stored)
     Transcribe {
-        /// Path to WAV file
-        file: String,
+        /// Path(s) to WAV file(s), or http(s) URLs to download to a temporary directory first
+        #[arg(required = true, num_args = 1..)]
+        files: Vec<String>,
         /// Language code (e.g. en, it, ar). Omit for auto-detection
         #[arg(long)]
         language: Option<String>,
+        /// Normalize audio loudness before transcription (helps quiet recordings)
+        #[arg(long)]
+        normalize: bool,
+        /// Number of files to transcribe concurrently (shares one loaded model)
+        #[arg(short, long, default_value = "1")]
+        jobs: usize,
+        /// Print each segment as soon as whisper produces it (files run one at a time)
+        #[arg(long, conflicts_with = "jobs")]
+        stream: bool,
+        /// Detect the language from only the first SECONDS of audio, then keep it fixed
+        #[arg(long, value_name = "SECONDS", conflicts_with = "language")]
+        detect_window: Option<f64>,
+        /// Add this to every timestamp, e.g. where a clipped download starts (90.5, 1:30 or 1:02:03)
+        #[arg(long, value_parser = parse_clock_arg, default_value = "0")]
+        offset: f64,
+        /// Split segments longer than N characters at word boundaries
+        #[arg(long, value_name = "N")]
+        max_segment_chars: Option<usize>,
+        /// Drop segments shorter than SECONDS (whisper's sub-second noise fragments)
+        #[arg(long, value_name = "SECONDS")]
+        min_segment_duration: Option<f64>,
+        /// Only produce text: skip timestamps, segments span ~30s windows
+        #[arg(long, conflicts_with = "max_segment_chars")]
+        no_timestamps: bool,
+        /// Decoding preset: accurate (beam 8), fast (greedy) or clean (against hallucinations)
+        #[arg(long, value_enum)]
+        preset: Option<transcriber::Preset>,
+        /// Keep whisper from emitting non-speech tokens like [Music] or (applause)
+        #[arg(long)]
+        suppress_nonspeech: bool,
+        /// Let whisper start a segment with a blank token (suppressed by default)
+        #[arg(long)]
+        no_suppress_blank: bool,
+        /// Start transcribing this far into each file (e.g. 600, 10:00); timestamps stay absolute
+        #[arg(long, value_name = "TIME", value_parser = parse_clock_arg)]
+        from: Option<f64>,
+        /// Transcribe only this many seconds (or MM:SS) from the start point
+        #[arg(long, value_name = "SECONDS", value_parser = parse_duration_arg)]
+        duration: Option<f64>,
+        /// Output format [default: table; with --output, from the extension, else table]
+        #[arg(long, value_enum, conflicts_with = "stream")]
+        format: Option<OutputFormat>,
+        /// Write the transcript to FILE instead of stdout (with several inputs: a directory,
+        /// one file per input)
+        #[arg(short, long, value_name = "FILE", conflicts_with = "stream")]
+        output: Option<String>,
     },
 
     /// Semantic search over stored transcripts
     Search {
-        /// Search query
-        query: String,
+        /// Search query (use `-` to read it from stdin)
+        #[arg(required_unless_present = "query_file", conflicts_with = "query_file")]
+        query: Option<String>,
+        /// Read the search query from a file
+        #[arg(long)]
+        query_file: Option<String>,
         /// Number of results
         #[arg(short, long, default_value = "5")]
         n: usize,
         /// Filter by video ID
         #[arg(long)]
         video_id: Option<String>,
+        /// Only search videos transcribed in this language (e.g. en, it)
+        #[arg(long)]
+        language: Option<String>,
+        /// Only search videos with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Drop hits further than this distance from the query
+        #[arg(long, value_name = "DISTANCE")]
+        max_distance: Option<f32>,
+        /// Print only the number of hits (of the top -n), for scripts
+        #[arg(long, conflicts_with_all = ["export", "export_json", "explain", "format", "debug"])]
+        count: bool,
+        /// Rescore the nearest hits with an LLM via Ollama and reorder them (slower, more precise)
+        #[arg(long, conflicts_with = "count")]
+        rerank: bool,
+        /// Number of vector hits to rerank before keeping the top -n
+        #[arg(long, value_name = "K", default_value = "20", requires = "rerank")]
+        rerank_top: usize,
+        /// Ollama model that scores the hits for --rerank
+        #[arg(long, value_name = "MODEL", default_value = rerank::DEFAULT_RERANK_MODEL,
+              requires = "rerank")]
+        rerank_model: String,
+        /// Fail instead of warning when the store was embedded with another model
+        #[arg(long)]
+        strict: bool,
+        /// Print raw index keys and whether each one resolved to metadata
+        #[arg(long)]
+        debug: bool,
+        /// HNSW search expansion for this query (higher = better recall, slower)
+        #[arg(long)]
+        ef_search: Option<usize>,
+        /// For each hit, show the words it shares with the query and its neighbouring segments
+        #[arg(long)]
+        explain: bool,
+        /// Store to search; repeat to search several and merge the results by distance
+        #[arg(long = "store-dir", value_name = "DIR", default_value = STORE_DIR)]
+        store_dirs: Vec<String>,
+        /// Output format for the results [default: table]
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Also write the results to FILE (`-` for stdout), format taken from the extension
+        /// (CSV if unknown)
+        #[arg(long, value_name = "FILE", conflicts_with = "export_json")]
+        export: Option<String>,
+        /// Also write the results as a JSON array to FILE (`-` for stdout)
+        #[arg(long, value_name = "FILE")]
+        export_json: Option<String>,
+        /// Start CSV output with a UTF-8 byte order mark so Excel detects the encoding
+        #[arg(long)]
+        bom: bool,
+        /// Cut the Text column of the results table to N characters (0 = full text)
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_TRUNCATE)]
+        truncate: usize,
     },
 
-    /// Export stored transcript as table + CSV
+    /// Print or write a stored transcript
     Export {
-        /// Video ID to export
-        video_id: String,
-        /// Output CSV file path
+        /// Video ID to export, or a unique prefix of it
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        video_id: Option<String>,
+        /// Output file path, `-` for stdout (with --all: a directory, one file per video)
         #[arg(short, long)]
         output: Option<String>,
+        /// Output format [default: table on screen; with --output, from the extension, else csv]
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Segment order for the table and the written file
+        #[arg(long, value_enum, default_value = "start")]
+        sort: ExportSort,
+        /// Reverse the segment order, e.g. latest first with the default --sort start
+        #[arg(long)]
+        reverse: bool,
+        /// Start CSV output with a UTF-8 byte order mark so Excel detects the encoding
+        #[arg(long)]
+        bom: bool,
+        /// Export whisper's untouched text instead of the cleaned-up text
+        #[arg(long)]
+        raw: bool,
+        /// Cut the Text column of tables to N characters (0 = full text)
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_TRUNCATE)]
+        truncate: usize,
+        /// Export every stored video
+        #[arg(long)]
+        all: bool,
+        /// Read segments from this `metadata.json` (or `metadata.bin`) instead of opening the store
+        #[arg(long, value_name = "FILE", conflicts_with = "all")]
+        segments_file: Option<String>,
+        /// With --all, only videos uploaded on or after this date (YYYY-MM-DD)
+        #[arg(long, requires = "all", value_parser = parse_date)]
+        since: Option<String>,
+        /// With --all, only videos uploaded on or before this date (YYYY-MM-DD)
+        #[arg(long, requires = "all", value_parser = parse_date)]
+        until: Option<String>,
+        /// Add the rows to the CSV file instead of replacing it (with --all: every video
+        /// into that one file), with a video_id column
+        #[arg(long, requires = "output")]
+        append: bool,
+    },
+
+    /// List stored videos
+    List {
+        /// Only videos uploaded on or after this date (YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        since: Option<String>,
+        /// Only videos uploaded on or before this date (YYYY-MM-DD)
+        #[arg(long, value_parser = parse_date)]
+        until: Option<String>,
+        /// Only videos with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// List videos in reverse order
+        #[arg(long)]
+        reverse: bool,
+    },
+
+    /// Store an existing SRT/VTT/CSV/JSON transcript, skipping download and whisper
+    Embed {
+        /// Transcript file
+        file: String,
+        /// Video ID to store the segments under (replaces its stored segments)
+        #[arg(value_parser = parse_store_name)]
+        video_id: String,
+        /// Transcript format [default: from the file extension]
+        #[arg(long, value_enum)]
+        format: Option<import::ImportFormat>,
+        /// Tag the video, e.g. --tags podcast,2024 (replaces its previous tags)
+        #[arg(long, value_delimiter = ',', value_parser = parse_tag)]
+        tags: Vec<String>,
+        /// Store to add the transcript to
+        #[arg(long, default_value = STORE_DIR)]
+        store_dir: String,
     },
 
     /// Full pipeline: download → transcribe → store
@@ -74,162 +371,2127 @@ enum Command {
         /// Language code (e.g. en, it, ar). Omit for auto-detection
         #[arg(long)]
         language: Option<String>,
+        /// Normalize audio loudness before transcription (helps quiet recordings)
+        #[arg(long)]
+        normalize: bool,
+        /// Detect the language from only the first SECONDS of audio, then keep it fixed
+        #[arg(long, value_name = "SECONDS", conflicts_with = "language")]
+        detect_window: Option<f64>,
+        /// Split segments longer than N characters at word boundaries
+        #[arg(long, value_name = "N")]
+        max_segment_chars: Option<usize>,
+        /// Drop segments shorter than SECONDS (whisper's sub-second noise fragments)
+        #[arg(long, value_name = "SECONDS")]
+        min_segment_duration: Option<f64>,
+        /// Only produce text: skip timestamps, segments span ~30s windows
+        #[arg(long, conflicts_with = "max_segment_chars")]
+        no_timestamps: bool,
+        /// Decoding preset: accurate (beam 8), fast (greedy) or clean (against hallucinations)
+        #[arg(long, value_enum)]
+        preset: Option<transcriber::Preset>,
+        /// Keep whisper from emitting non-speech tokens like [Music] or (applause)
+        #[arg(long)]
+        suppress_nonspeech: bool,
+        /// Let whisper start a segment with a blank token (suppressed by default)
+        #[arg(long)]
+        no_suppress_blank: bool,
+        /// Only process from this time on; timestamps stay aligned to the full video
+        #[arg(long, value_parser = parse_clock_arg)]
+        start: Option<f64>,
+        /// Only process up to this time
+        #[arg(long, value_parser = parse_clock_arg)]
+        end: Option<f64>,
+        /// Ask yt-dlp for the canonical video ID instead of parsing the URL
+        #[arg(long)]
+        resolve_id: bool,
+        /// Retry flaky downloads: passed to yt-dlp, and reruns it if conversion is left unfinished
+        #[arg(long, value_name = "N")]
+        retries: Option<u32>,
+        /// Directory the downloaded WAV is saved in
+        #[arg(short, long, value_name = "DIR", default_value = DOWNLOADS_DIR)]
+        output_dir: String,
+        /// Store the transcript under NAME instead of the video ID (e.g. a clip beside the full video)
+        #[arg(long = "as", value_name = "NAME", value_parser = parse_store_name)]
+        store_as: Option<String>,
+        /// Download again, replacing an existing WAV of the same name even if it holds
+        /// another video's audio
+        #[arg(long)]
+        force: bool,
+        /// Keep the downloaded WAV after storing (the default)
+        #[arg(long, conflicts_with = "cleanup")]
+        keep_audio: bool,
+        /// Delete the downloaded WAV once its segments are stored
+        #[arg(long)]
+        cleanup: bool,
+        /// Skip segments whose whisper confidence (0.0-1.0) is below this value
+        #[arg(long)]
+        min_confidence: Option<f32>,
+        /// Add segments after the video's existing ones instead of overwriting them
+        #[arg(long)]
+        append: bool,
+        /// When the video is already stored: overwrite it, skip it, or fail
+        #[arg(long, value_enum, default_value = "overwrite", conflicts_with = "append")]
+        on_exists: pipeline::OnExists,
+        /// Record the outcome in this JSON manifest, replacing the URL's previous entry
+        #[arg(long, value_name = "FILE")]
+        manifest: Option<String>,
+        /// Tag the video, e.g. --tags podcast,2024 [default: keep its previous tags]
+        #[arg(long, value_delimiter = ',', value_parser = parse_tag)]
+        tags: Vec<String>,
+        /// Store segments while transcribing, so a long video is searchable as it goes
+        #[arg(long, conflicts_with = "min_confidence")]
+        stream: bool,
+        /// HNSW graph connectivity, fixed when the store is created [default: 16]
+        #[arg(long)]
+        connectivity: Option<usize>,
+        /// HNSW expansion while adding vectors, fixed when the store is created [default: 128]
+        #[arg(long)]
+        ef_construction: Option<usize>,
+        /// HNSW expansion while searching [default: 64]
+        #[arg(long)]
+        ef_search: Option<usize>,
+        /// Segment metadata file format; switching converts it on this write [default: keep]
+        #[arg(long, value_enum)]
+        metadata_format: Option<store::MetadataFormat>,
+    },
+
+    /// Transcribe every WAV file in a directory and store each under its file name
+    PipelineLocal {
+        /// Directory holding the WAV files (not searched recursively)
+        dir: String,
+        /// Language code (e.g. en, it, ar). Omit for auto-detection
+        #[arg(long)]
+        language: Option<String>,
+        /// Normalize audio loudness before transcription (helps quiet recordings)
+        #[arg(long)]
+        normalize: bool,
+        /// Number of files to transcribe concurrently (shares one loaded model)
+        #[arg(short, long, default_value = "1")]
+        jobs: usize,
+        /// Detect the language from only the first SECONDS of audio, then keep it fixed
+        #[arg(long, value_name = "SECONDS", conflicts_with = "language")]
+        detect_window: Option<f64>,
+        /// Split segments longer than N characters at word boundaries
+        #[arg(long, value_name = "N")]
+        max_segment_chars: Option<usize>,
+        /// Drop segments shorter than SECONDS (whisper's sub-second noise fragments)
+        #[arg(long, value_name = "SECONDS")]
+        min_segment_duration: Option<f64>,
+        /// Decoding preset: accurate (beam 8), fast (greedy) or clean (against hallucinations)
+        #[arg(long, value_enum)]
+        preset: Option<transcriber::Preset>,
+        /// Keep whisper from emitting non-speech tokens like [Music] or (applause)
+        #[arg(long)]
+        suppress_nonspeech: bool,
+        /// Let whisper start a segment with a blank token (suppressed by default)
+        #[arg(long)]
+        no_suppress_blank: bool,
+        /// Skip segments whose whisper confidence (0.0-1.0) is below this value
+        #[arg(long)]
+        min_confidence: Option<f32>,
+        /// When a file is already stored: overwrite it, skip it, or report it as failed
+        #[arg(long, value_enum, default_value = "overwrite")]
+        on_exists: pipeline::OnExists,
+        /// Tag every stored file, e.g. --tags podcast,2024 [default: keep previous tags]
+        #[arg(long, value_delimiter = ',', value_parser = parse_tag)]
+        tags: Vec<String>,
+        /// Store to add the transcripts to
+        #[arg(long, default_value = STORE_DIR)]
+        store_dir: String,
+    },
+
+    /// Show whether a video is stored, with its segment count and metadata
+    Info {
+        /// Video ID to look up (exits with status 1 if it isn't stored)
+        video_id: String,
+    },
+
+    /// Print one stored segment's timing and full text
+    Get {
+        /// Video ID
+        video_id: String,
+        /// Segment index, 0-based as in JSON exports
+        index: usize,
+    },
+
+    /// Find segments similar to a stored one, across all videos ("more like this")
+    Related {
+        /// Video ID, or a unique prefix of it
+        video_id: String,
+        /// Segment index, 0-based as in JSON exports
+        index: usize,
+        /// Number of results
+        #[arg(short, long, default_value = "5")]
+        n: usize,
+        /// Output format for the results [default: table]
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+        /// Cut the Text column of the results table to N characters (0 = full text)
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_TRUNCATE)]
+        truncate: usize,
+    },
+
+    /// Remove stored videos whose downloaded WAV no longer exists
+    Prune {
+        /// Directory holding downloaded WAVs
+        #[arg(short, long, default_value = DOWNLOADS_DIR)]
+        downloads: String,
+        /// Only report orphaned videos, don't delete anything
+        #[arg(long, visible_alias = "missing-only")]
+        dry_run: bool,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Remove stored videos and their segments
+    Delete {
+        /// Video IDs to remove, or unique prefixes of them
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        video_ids: Vec<String>,
+        /// Remove every stored video
+        #[arg(long)]
+        all: bool,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Check that the vector index and metadata agree
+    Verify {
+        /// Remove index entries without metadata (segments without vectors are kept)
+        #[arg(long)]
+        repair: bool,
+        /// Back up an unreadable index and rebuild it by re-embedding the stored metadata
+        #[arg(long, conflicts_with = "repair")]
+        force_reset: bool,
+        /// Embed segments again whose embedding failed during storing
+        #[arg(long, conflicts_with_all = ["repair", "force_reset"])]
+        retry_failed: bool,
+    },
+
+    /// Re-embed stored texts with nomic-embed-text task prefixes and rebuild the index
+    Reembed {
+        /// Store to migrate
+        #[arg(long, default_value = STORE_DIR)]
+        store_dir: String,
+    },
+
+    /// Print versions, the whisper model in use and the store's embedding model
+    Version {
+        /// Store whose recorded embedding model is shown
+        #[arg(long, default_value = STORE_DIR)]
+        store_dir: String,
     },
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    if let Some(path) = cli.config.clone() {
+        match ConfigFile::read(&path) {
+            Ok(config) => config.apply(&mut cli),
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
 
     if cli.verbose {
         eprintln!("[verbose mode enabled]");
     }
+    let color = use_color(cli.no_color);
+    let verbose = cli.verbose;
+    let model_dir = cli.model_dir.as_deref();
+    let keep_alive = cli.ollama_keepalive.as_deref();
+    let embed_model = cli.embed_model.as_deref();
+    let no_persist = cli.no_persist;
+    if no_persist {
+        eprintln!("note: --no-persist: the store is not written, changes won't survive this run");
+    }
+    let render = export::RenderOptions {
+        pretty_json: cli.pretty,
+        ..Default::default()
+    };
 
     match cli.command {
-        Command::Download { url, output, start, end } => {
-            let clip = match (start, end) {
-                (Some(s), Some(e)) => Some((s, e)),
-                (Some(_), None) | (None, Some(_)) => {
-                    eprintln!("error: --start and --end must both be provided");
+        Command::Download {
+            url,
+            output,
+            start,
+            end,
+            resolve_id,
+            write_info_json,
+            retries,
+            keep_intermediate,
+            ffmpeg_args,
+            name,
+            force,
+        } => {
+            let Some(clip) = clip_range(start, end) else {
+                return;
+            };
+            let ffmpeg_args = match ffmpeg_args.as_deref().map(downloader::parse_ffmpeg_args) {
+                Some(Ok(args)) => args,
+                Some(Err(e)) => {
+                    eprintln!("error: {e}");
                     return;
                 }
-                (None, None) => None,
+                None => Vec::new(),
             };
-            match downloader::download(&url, &output, clip) {
-                Ok(path) => println!("downloaded: {}", path.display()),
-                Err(e) => eprintln!("error: {e}"),
+            let url = if resolve_id {
+                match downloader::resolve_video_id(&url, true) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return;
+                    }
+                }
+            } else {
+                url
+            };
+            let opts = downloader::DownloadOptions {
+                clip,
+                write_info_json,
+                retries,
+                keep_intermediate,
+                ffmpeg_args,
+                name,
+                force,
+            };
+            if output == "-" {
+                if write_info_json {
+                    eprintln!("warning: --write-info-json is ignored when streaming to stdout");
+                }
+                if let Err(e) = downloader::download_to_stdout(&url, &opts) {
+                    eprintln!("error: {e}");
+                }
+                return;
             }
-        }
-        Command::Transcribe { file, language } => {
-            match transcriber::transcribe(&file, language.as_deref(), None) {
-                Ok(segments) => {
-                    println!("{}", transcriber::format_table(&segments));
-                    println!("{} segment(s)", segments.len());
+            match downloader::download(&url, &output, &opts) {
+                Ok(audio) => {
+                    if audio.offset_seconds > 0.0 {
+                        println!(
+                            "downloaded: {} (starts at {} in the video)",
+                            audio.path.display(),
+                            format_clock(audio.offset_seconds)
+                        );
+                    } else {
+                        println!("downloaded: {}", audio.path.display());
+                    }
+                    if let Some(path) = &audio.intermediate {
+                        println!("intermediate: {}", path.display());
+                    }
                 }
                 Err(e) => eprintln!("error: {e}"),
             }
         }
-        Command::Search { query, n, video_id } => {
-            match store::VectorStore::open(STORE_DIR) {
-                Ok(vs) => match vs.search(&query, n, video_id.as_deref()) {
-                    Ok(results) if results.is_empty() => {
-                        println!("no results found");
-                    }
-                    Ok(results) => {
-                        let mut table = comfy_table::Table::new();
-                        table.set_header(["#", "Video", "Time", "Text", "Distance"]);
-                        for (i, r) in results.iter().enumerate() {
-                            table.add_row([
-                                (i + 1).to_string(),
-                                r.video_id.clone(),
-                                format!(
-                                    "{}-{}",
-                                    format_ts(r.start),
-                                    format_ts(r.end)
-                                ),
-                                r.text.clone(),
-                                format!("{:.4}", r.distance),
-                            ]);
+        Command::Transcribe {
+            files,
+            language,
+            normalize,
+            jobs,
+            stream,
+            detect_window,
+            offset,
+            max_segment_chars,
+            min_segment_duration,
+            no_timestamps,
+            preset,
+            suppress_nonspeech,
+            no_suppress_blank,
+            from,
+            duration,
+            format,
+            output,
+        } => {
+            let multiple = files.len() > 1;
+            // A single output file names its format; a directory gets `--format` files
+            let single_output = output.as_deref().filter(|_| !multiple);
+            let format = format
+                .or_else(|| single_output.and_then(OutputFormat::from_path))
+                .unwrap_or(OutputFormat::Table);
+            if let Some(dir) = output.as_ref().filter(|_| multiple)
+                && let Err(e) = std::fs::create_dir_all(dir)
+            {
+                eprintln!("error: {dir}: {e}");
+                return;
+            }
+            let opts = transcriber::TranscribeOptions {
+                language: language.as_deref(),
+                model_dir,
+                normalize,
+                detect_window,
+                offset_seconds: offset,
+                max_segment_chars,
+                min_segment_duration,
+                no_timestamps,
+                from_seconds: from.unwrap_or(0.0),
+                duration_seconds: duration,
+                decoding: decoding(preset, suppress_nonspeech, no_suppress_blank),
+                score_languages: verbose,
+                ..Default::default()
+            };
+
+            // URLs are downloaded into a scratch directory that is removed when `temp` drops
+            let temp = TempDir(std::env::temp_dir().join(format!("sawtrs-{}", std::process::id())));
+            let mut wavs = Vec::with_capacity(files.len());
+            let progress = overall_progress(files.iter().filter(|f| is_url(f)).count(), "videos");
+            for file in &files {
+                if !is_url(file) {
+                    wavs.push(file.clone());
+                    continue;
+                }
+                progress.suspend(|| eprintln!("downloading {file}..."));
+                match downloader::download(file, &temp.0.to_string_lossy(), &Default::default()) {
+                    Ok(audio) => wavs.push(audio.path.to_string_lossy().into_owned()),
+                    Err(e) => {
+                        progress.finish_and_clear();
+                        eprintln!("error: {file}: {e}");
+                        return;
+                    }
+                }
+                progress.inc(1);
+            }
+            progress.finish_and_clear();
+
+            if verbose {
+                for wav in &wavs {
+                    report_wav(wav);
+                }
+            }
+
+            if stream {
+                for (file, wav) in files.iter().zip(&wavs) {
+                    if multiple {
+                        println!("== {file} ==");
+                    }
+                    let mut count = 0;
+                    let result = transcriber::transcribe_streaming(wav, &opts, |seg| {
+                        count += 1;
+                        println!(
+                            "{count:>4}  {} → {}  {}",
+                            format_clock(seg.start),
+                            format_clock(seg.end),
+                            seg.text
+                        );
+                    });
+                    match result {
+                        Ok(transcript) => {
+                            if let Some(warning) = &transcript.input_warning {
+                                eprintln!("warning: {warning}");
+                            }
+                            if let Some(warning) = &transcript.warning {
+                                eprintln!("warning: {warning}");
+                            }
+                            report_language(&transcript, &opts);
+                            if verbose {
+                                report_candidates(&transcript);
+                                report_speed(&transcript);
+                            }
+                            if transcript.has_speech() {
+                                println!("{} segment(s)", transcript.segments.len());
+                            } else {
+                                println!("no speech detected in {file}");
+                            }
                         }
-                        println!("{table}");
-                        println!("{} result(s)", results.len());
+                        Err(e) => eprintln!("error: {file}: {e}"),
                     }
-                    Err(e) => eprintln!("error: {e}"),
-                },
-                Err(e) => eprintln!("error: {e}"),
+                }
+                return;
             }
-        }
-        Command::Export { video_id, output } => {
-            match store::VectorStore::open(STORE_DIR) {
-                Ok(vs) => match vs.get_segments(&video_id) {
-                    Ok(segments) => {
-                        let export_segs: Vec<export::ExportSegment> = segments
+
+            if jobs > transcriber::MAX_JOBS {
+                eprintln!(
+                    "warning: --jobs {jobs} exceeds the limit of {}, each job loads a full whisper state",
+                    transcriber::MAX_JOBS
+                );
+            }
+            let results = match transcriber::transcribe_many(&wavs, &opts, jobs) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            for ((file, wav), result) in files.iter().zip(&wavs).zip(results) {
+                if multiple && format == OutputFormat::Table && output.is_none() {
+                    println!("== {file} ==");
+                }
+                match result {
+                    Ok(transcript) => {
+                        if let Some(warning) = &transcript.input_warning {
+                            eprintln!("warning: {warning}");
+                        }
+                        if let Some(warning) = &transcript.warning {
+                            eprintln!("warning: {warning}");
+                        }
+                        report_language(&transcript, &opts);
+                        if verbose {
+                            report_candidates(&transcript);
+                            report_speed(&transcript);
+                        }
+                        if format == OutputFormat::Table && !transcript.has_speech() {
+                            println!("no speech detected in {file}");
+                            continue;
+                        }
+                        let segments: Vec<export::ExportSegment> = transcript
+                            .segments
                             .iter()
-                            .map(|s| export::ExportSegment {
-                                index: s.index,
+                            .enumerate()
+                            .map(|(i, s)| export::ExportSegment {
+                                index: i,
                                 start: s.start,
                                 end: s.end,
                                 text: s.text.clone(),
                             })
                             .collect();
-
-                        println!("{}", export::format_table(&video_id, &export_segs));
-                        println!("{} segment(s)", export_segs.len());
-
-                        if let Some(path) = output {
-                            match export::write_csv(&path, &export_segs) {
-                                Ok(()) => println!("written to {path}"),
-                                Err(e) => eprintln!("csv error: {e}"),
+                        let list = export::SegmentList {
+                            video_id: None,
+                            file: Some(file),
+                            language: transcript.language.as_deref(),
+                            segments: if transcript.has_speech() { &segments } else { &[] },
+                        };
+                        if let Some(out) = &output {
+                            let path = if multiple {
+                                let stem = Path::new(wav).file_stem().unwrap_or_default();
+                                let name = format!("{}.{}", stem.display(), format.extension());
+                                Path::new(out).join(name).display().to_string()
+                            } else {
+                                out.clone()
+                            };
+                            let rows = export::Rows::Segments(list);
+                            match export::render_to(&path, format, rows, render) {
+                                Ok(()) => println!(
+                                    "written to {path} ({} segment(s))",
+                                    list.segments.len()
+                                ),
+                                Err(e) => eprintln!("error: {path}: {e}"),
                             }
+                            continue;
+                        }
+                        write_rows("-", format, export::Rows::Segments(list), render);
+                        if format == OutputFormat::Table {
+                            println!("{} segment(s)", segments.len());
                         }
                     }
-                    Err(e) => eprintln!("error: {e}"),
-                },
-                Err(e) => eprintln!("error: {e}"),
+                    Err(e) => eprintln!("error: {file}: {e}"),
+                }
             }
         }
-        Command::Pipeline { url, language } => {
-            // Step 1: Download
-            eprintln!("[1/3] downloading audio...");
-            let wav_path = match downloader::download(&url, "downloads", None) {
-                Ok(path) => {
-                    eprintln!("       saved to {}", path.display());
-                    path
-                }
+        Command::Search {
+            query,
+            query_file,
+            n,
+            video_id,
+            language,
+            tag,
+            max_distance,
+            count,
+            rerank,
+            rerank_top,
+            rerank_model,
+            strict,
+            debug,
+            ef_search,
+            explain,
+            store_dirs,
+            format,
+            export,
+            export_json,
+            bom,
+            truncate,
+        } => {
+            let render = export::RenderOptions {
+                max_table_text: (truncate > 0).then_some(truncate),
+                ..render
+            };
+            let format = format.unwrap_or(OutputFormat::Table);
+            let query = match resolve_query(query.as_deref(), query_file.as_deref()) {
+                Ok(q) => q,
                 Err(e) => {
-                    eprintln!("error: {e}");
+                    eprintln!("error: could not read query: {e}");
                     return;
                 }
             };
+            if query.is_empty() {
+                eprintln!("error: search query is empty");
+                return;
+            }
+            let tuning = store::IndexTuning {
+                expansion_search: ef_search,
+                ..Default::default()
+            };
+            let interrupt = install_interrupt_handler();
+            let mut stores = Vec::with_capacity(store_dirs.len());
+            for dir in &store_dirs {
+                match store::VectorStore::open_view(dir, tuning) {
+                    Ok(mut vs) => {
+                        vs.set_cancel_flag(Arc::clone(&interrupt));
+                        if let Some(keep_alive) = keep_alive {
+                            vs.set_keep_alive(keep_alive);
+                        }
+                        if let Some(model) = embed_model {
+                            vs.set_embedding_model(model);
+                        }
+                        if let Err(e) = vs.check_embedding_model() {
+                            if strict {
+                                eprintln!("error: {dir}: {e}");
+                                return;
+                            }
+                            eprintln!("warning: {dir}: {e}");
+                        }
+                        stores.push(vs);
+                    }
+                    Err(e) => {
+                        eprintln!("error: {dir}: {e}");
+                        return;
+                    }
+                }
+            }
+            let federated = stores.len() > 1;
+            if let Some(lang) = &language
+                && !stores.iter().any(|vs| vs.has_language(lang))
+            {
+                eprintln!("error: no stored videos in language `{lang}`");
+                return;
+            }
+            if let Some(tag) = &tag
+                && !stores.iter().any(|vs| vs.has_tag(tag))
+            {
+                eprintln!("error: no stored videos tagged `{tag}`");
+                return;
+            }
+            let filter = store::SearchFilter {
+                video_id: video_id.as_deref(),
+                language: language.as_deref(),
+                documents: Some(false),
+                tag: tag.as_deref(),
+            };
+
+            let candidates = if rerank { n.max(rerank_top) } else { n };
+            let mut results =
+                match search_stores(&stores, &store_dirs, &query, candidates, filter, debug) {
+                    Ok(results) => results,
+                    Err(e @ store::StoreError::Interrupted) => {
+                        eprintln!("{e}");
+                        std::process::exit(130);
+                    }
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return;
+                    }
+                };
+            if let Some(max) = max_distance {
+                results.retain(|(_, r)| r.distance <= max);
+            }
+            if rerank && results.len() > 1 {
+                let mut reranker = rerank::OllamaReranker::new(&rerank_model);
+                reranker.set_cancel_flag(Arc::clone(&interrupt));
+                if let Some(keep_alive) = keep_alive {
+                    reranker.set_keep_alive(keep_alive);
+                }
+                eprintln!("reranking {} hit(s) with {rerank_model}...", results.len());
+                let texts: Vec<&str> = results.iter().map(|(_, r)| r.text.as_str()).collect();
+                match reranker.rerank(&query, &texts) {
+                    Ok(order) => {
+                        let mut slots: Vec<_> = results.into_iter().map(Some).collect();
+                        results =
+                            order.into_iter().filter_map(|i| slots.get_mut(i)?.take()).collect();
+                    }
+                    Err(e @ rerank::RerankError::Interrupted) => {
+                        eprintln!("{e}");
+                        std::process::exit(130);
+                    }
+                    Err(e) => eprintln!("warning: {e}; showing hits in vector order"),
+                }
+            }
+            results.truncate(n);
+            if count {
+                println!("{}", results.len());
+                return;
+            }
+
+            let hits: Vec<export::ExportHit> = results
+                .iter()
+                .enumerate()
+                .map(|(i, (source, r))| export::ExportHit {
+                    rank: i + 1,
+                    store: federated.then(|| store_dirs[*source].clone()),
+                    video_id: r.video_id.clone(),
+                    start: r.start,
+                    end: r.end,
+                    text: r.text.clone(),
+                    distance: r.distance,
+                    confidence: r.confidence,
+                    url: downloader::deep_link(stores[*source].source_id(&r.video_id), r.start),
+                })
+                .collect();
+
+            // --export FILE picks its format from the extension (CSV if unknown);
+            // with `-` the export goes to stdout in place of the table
+            let target = match (&export, &export_json) {
+                (Some(path), _) => Some((
+                    path.as_str(),
+                    OutputFormat::from_path(path).unwrap_or(OutputFormat::Csv),
+                )),
+                (None, Some(path)) => Some((path.as_str(), OutputFormat::Json)),
+                (None, None) => None,
+            };
+            let to_stdout = target.is_some_and(|(path, _)| path == "-");
+
+            if format == OutputFormat::Table && !to_stdout {
+                if hits.is_empty() {
+                    println!("no results found");
+                } else {
+                    let shown: Vec<export::ExportHit> = hits
+                        .iter()
+                        .map(|h| export::ExportHit {
+                            text: if color {
+                                highlight_terms(&h.text, &overlap_terms(&query, &h.text))
+                            } else {
+                                h.text.clone()
+                            },
+                            ..h.clone()
+                        })
+                        .collect();
+                    write_rows("-", format, export::Rows::Hits(&shown), render);
+                    println!("{} result(s)", hits.len());
+                    if explain {
+                        print_explanations(&stores, &query, &results);
+                    }
+                }
+            } else if !to_stdout {
+                write_rows("-", format, export::Rows::Hits(&hits), render);
+            }
 
-            // Step 2: Transcribe
-            eprintln!("[2/3] transcribing...");
-            let segments = match transcriber::transcribe(
-                wav_path.to_str().unwrap_or_default(),
-                language.as_deref(),
-                None,
-            ) {
-                Ok(segs) => {
-                    eprintln!("       {} segment(s)", segs.len());
-                    segs
+            if let Some((path, format)) = target {
+                let opts = export::RenderOptions { bom, ..render };
+                write_rows(path, format, export::Rows::Hits(&hits), opts);
+            }
+        }
+        Command::Export {
+            video_id,
+            output,
+            format,
+            sort,
+            reverse,
+            bom,
+            raw,
+            truncate,
+            all,
+            segments_file,
+            since,
+            until,
+            append,
+        } => {
+            let opts = export::RenderOptions {
+                bom,
+                max_table_text: (truncate > 0).then_some(truncate),
+                append,
+                ..render
+            };
+            if append {
+                if output.as_deref() == Some("-") {
+                    eprintln!("error: --append needs a file, not stdout");
+                    return;
+                }
+                if format.is_some_and(|f| f != OutputFormat::Csv) {
+                    eprintln!("error: --append only works with CSV");
+                    return;
                 }
+            }
+            // Appending always writes CSV, whatever the file is called
+            let format = if append { Some(OutputFormat::Csv) } else { format };
+            let order = ExportOrder { sort, reverse };
+            if let Some(file) = segments_file {
+                let video_id = video_id.unwrap_or_default();
+                let segments = store::read_segments_file(Path::new(&file), &video_id);
+                export_video(segments, &video_id, output.as_deref(), format, order, raw, opts);
+                return;
+            }
+
+            let vs = match store::VectorStore::open_view(STORE_DIR, Default::default()) {
+                Ok(vs) => vs,
                 Err(e) => {
                     eprintln!("error: {e}");
                     return;
                 }
             };
 
-            // Step 3: Store
-            eprintln!("[3/3] storing in vector database...");
-            let video_id = match downloader::extract_video_id(&url) {
-                Ok(id) => id,
+            if !all {
+                let video_id = match vs.resolve_video_id(&video_id.unwrap_or_default()) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return;
+                    }
+                };
+                let segments = vs.get_segments(&video_id);
+                export_video(segments, &video_id, output.as_deref(), format, order, raw, opts);
+                return;
+            }
+
+            let ids = vs.get_video_ids_uploaded(since.as_deref(), until.as_deref());
+            if ids.is_empty() {
+                println!("no videos match");
+                return;
+            }
+            let to_stdout = output.as_deref() == Some("-");
+            if let Some(dir) = output.as_ref().filter(|_| !to_stdout && !append)
+                && let Err(e) = std::fs::create_dir_all(dir)
+            {
+                eprintln!("error: {dir}: {e}");
+                return;
+            }
+            for id in &ids {
+                let path = output.as_ref().map(|dir| {
+                    if to_stdout || append {
+                        dir.clone()
+                    } else {
+                        let ext = format.unwrap_or(OutputFormat::Csv).extension();
+                        let file = format!("{id}.{ext}");
+                        Path::new(dir).join(file).display().to_string()
+                    }
+                });
+                let segments = vs.get_segments(id);
+                export_video(segments, id, path.as_deref(), format, order, raw, opts);
+            }
+            if !to_stdout {
+                println!("{} video(s) exported", ids.len());
+            }
+        }
+        Command::List {
+            since,
+            until,
+            tag,
+            reverse,
+        } => {
+            let vs = match store::VectorStore::open_view(STORE_DIR, Default::default()) {
+                Ok(vs) => vs,
                 Err(e) => {
                     eprintln!("error: {e}");
                     return;
                 }
             };
 
-            let store_segments: Vec<store::TranscriptSegment> = segments
-                .iter()
-                .map(|s| store::TranscriptSegment {
-                    start: s.start,
-                    end: s.end,
-                    text: s.text.clone(),
-                })
-                .collect();
+            let mut ids = vs.get_video_ids_uploaded(since.as_deref(), until.as_deref());
+            if let Some(tag) = &tag {
+                ids.retain(|id| vs.video_has_tag(id, tag));
+            }
+            if reverse {
+                ids.reverse();
+            }
+            if ids.is_empty() {
+                println!("no videos stored");
+                return;
+            }
 
-            match store::VectorStore::open(STORE_DIR) {
-                Ok(mut vs) => match vs.store_transcript(&video_id, &store_segments) {
-                    Ok(n) => eprintln!("       stored {n} segment(s) for {video_id}"),
-                    Err(e) => eprintln!("error: {e}"),
-                },
-                Err(e) => eprintln!("error: {e}"),
+            let mut table = comfy_table::Table::new();
+            table.set_header(["Video", "Title", "Uploaded", "Language", "Tags", "Segments"]);
+            for id in &ids {
+                let info = vs.video_info(id);
+                let segments = vs.segment_count(id);
+                table.add_row([
+                    id.clone(),
+                    info.and_then(|i| i.title.clone()).unwrap_or_else(|| "-".into()),
+                    info.and_then(|i| i.upload_date.clone()).unwrap_or_else(|| "-".into()),
+                    info.and_then(|i| i.language.clone()).unwrap_or_else(|| "-".into()),
+                    info.map(|i| i.tags.join(", "))
+                        .filter(|t| !t.is_empty())
+                        .unwrap_or_else(|| "-".into()),
+                    segments.to_string(),
+                ]);
             }
+            println!("{table}");
+            println!("{} video(s)", ids.len());
         }
-    }
-}
+        Command::Info { video_id } => {
+            let vs = match store::VectorStore::open_view(STORE_DIR, Default::default()) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+
+            if !vs.has_video(&video_id) {
+                println!("{video_id}: not stored");
+                std::process::exit(1);
+            }
+
+            let info = vs.video_info(&video_id);
+            let field = |f: Option<String>| f.unwrap_or_else(|| "-".into());
+            let mut table = comfy_table::Table::new();
+            table.add_row(["Video".to_string(), video_id.clone()]);
+            table.add_row(["Segments".to_string(), vs.segment_count(&video_id).to_string()]);
+            table.add_row(["Title".to_string(), field(info.and_then(|i| i.title.clone()))]);
+            table.add_row(["Channel".to_string(), field(info.and_then(|i| i.channel.clone()))]);
+            table.add_row([
+                "Uploaded".to_string(),
+                field(info.and_then(|i| i.upload_date.clone())),
+            ]);
+            table.add_row([
+                "Duration".to_string(),
+                field(info.and_then(|i| i.duration).map(format_clock)),
+            ]);
+            table.add_row(["Language".to_string(), field(info.and_then(|i| i.language.clone()))]);
+            table.add_row([
+                "Tags".to_string(),
+                field(info.map(|i| i.tags.join(", ")).filter(|t| !t.is_empty())),
+            ]);
+            println!("{table}");
+        }
+        Command::Get { video_id, index } => {
+            let vs = match store::VectorStore::open_view(STORE_DIR, Default::default()) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
 
-fn format_ts(seconds: f64) -> String {
-    let mins = (seconds / 60.0) as u32;
-    let secs = seconds % 60.0;
-    format!("{mins:02}:{secs:05.2}")
+            let Some(seg) = vs.get_segment(&video_id, index) else {
+                match vs.segment_count(&video_id) {
+                    0 => eprintln!("error: {}", store::StoreError::VideoNotFound(video_id)),
+                    count => eprintln!(
+                        "error: index {index} out of range, {video_id} has {count} segment(s) (0-{})",
+                        count - 1
+                    ),
+                }
+                return;
+            };
+            println!(
+                "{video_id} #{index}  {} → {}",
+                format_clock(seg.start),
+                format_clock(seg.end)
+            );
+            println!("{}", seg.text);
+        }
+        Command::Related {
+            video_id,
+            index,
+            n,
+            format,
+            truncate,
+        } => {
+            let render = export::RenderOptions {
+                max_table_text: (truncate > 0).then_some(truncate),
+                ..render
+            };
+            let vs = match store::VectorStore::open_view(STORE_DIR, Default::default()) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            let video_id = match vs.resolve_video_id(&video_id) {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+
+            let filter = store::SearchFilter {
+                documents: Some(false),
+                ..Default::default()
+            };
+            let results = match vs.related(&video_id, index, n, filter) {
+                Ok(results) => results,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            let hits: Vec<export::ExportHit> = results
+                .iter()
+                .enumerate()
+                .map(|(i, r)| export::ExportHit {
+                    rank: i + 1,
+                    store: None,
+                    video_id: r.video_id.clone(),
+                    start: r.start,
+                    end: r.end,
+                    text: r.text.clone(),
+                    distance: r.distance,
+                    confidence: r.confidence,
+                    url: downloader::deep_link(vs.source_id(&r.video_id), r.start),
+                })
+                .collect();
+
+            let format = format.unwrap_or(OutputFormat::Table);
+            if format == OutputFormat::Table && hits.is_empty() {
+                println!("no related segments found");
+                return;
+            }
+            write_rows("-", format, export::Rows::Hits(&hits), render);
+            if format == OutputFormat::Table {
+                println!("{} result(s)", hits.len());
+            }
+        }
+        Command::Embed {
+            file,
+            video_id,
+            format,
+            tags,
+            store_dir,
+        } => {
+            let imported = match import::read_transcript(&file, format) {
+                Ok(segments) => segments,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            let segments: Vec<store::TranscriptSegment> = imported
+                .into_iter()
+                .map(|s| store::TranscriptSegment {
+                    start: s.start,
+                    end: s.end,
+                    text: s.text,
+                    confidence: None,
+                    raw_text: None,
+                })
+                .collect();
+
+            let mut vs = match open_writable(&store_dir, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            vs.set_cancel_flag(install_interrupt_handler());
+            if let Some(keep_alive) = keep_alive {
+                vs.set_keep_alive(keep_alive);
+            }
+            if let Some(model) = embed_model {
+                vs.set_embedding_model(model);
+            }
+
+            eprintln!("embedding {} segment(s) from {file}...", segments.len());
+            match vs.store_transcript(&video_id, &segments, None) {
+                Ok(outcome) => {
+                    println!("stored {} segment(s) for {video_id}", outcome.stored);
+                    if !tags.is_empty() {
+                        let info = store::VideoInfo {
+                            tags,
+                            ..vs.video_info(&video_id).cloned().unwrap_or_default()
+                        };
+                        if let Err(e) = vs.set_video_info(&video_id, info) {
+                            eprintln!("warning: could not save tags: {e}");
+                        }
+                    }
+                    if !outcome.failed.is_empty() {
+                        eprintln!(
+                            "warning: {} segment(s) failed to embed and are stored without vectors \
+                             (retry with `sawtrs verify --retry-failed`)",
+                            outcome.failed.len()
+                        );
+                    }
+                }
+                Err(e @ store::StoreError::Interrupted) => {
+                    eprintln!("{e}");
+                    std::process::exit(130);
+                }
+                Err(e) => eprintln!("error: {e}"),
+            }
+        }
+        Command::Pipeline {
+            url,
+            language,
+            normalize,
+            detect_window,
+            max_segment_chars,
+            min_segment_duration,
+            no_timestamps,
+            preset,
+            suppress_nonspeech,
+            no_suppress_blank,
+            start,
+            end,
+            resolve_id,
+            retries,
+            output_dir,
+            store_as,
+            force,
+            keep_audio: _,
+            cleanup,
+            min_confidence,
+            append,
+            on_exists,
+            manifest,
+            tags,
+            stream,
+            connectivity,
+            ef_construction,
+            ef_search,
+            metadata_format,
+        } => {
+            let Some(clip) = clip_range(start, end) else {
+                return;
+            };
+            if output_dir == "-" {
+                eprintln!("error: pipeline needs the WAV on disk, `-` is for download");
+                return;
+            }
+            let opts = pipeline::PipelineOptions {
+                transcribe: transcriber::TranscribeOptions {
+                    language: language.as_deref(),
+                    model_dir,
+                    normalize,
+                    detect_window,
+                    max_segment_chars,
+                    min_segment_duration,
+                    no_timestamps,
+                    decoding: decoding(preset, suppress_nonspeech, no_suppress_blank),
+                    score_languages: verbose,
+                    ..Default::default()
+                },
+                downloads_dir: &output_dir,
+                clip,
+                resolve_id,
+                retries,
+                store_as: store_as.as_deref(),
+                force_download: force,
+                cleanup,
+                min_confidence,
+                append,
+                on_exists,
+                tags: &tags,
+                stream,
+                ollama_keep_alive: keep_alive,
+                embed_model,
+                tuning: store::IndexTuning {
+                    connectivity,
+                    expansion_add: ef_construction,
+                    expansion_search: ef_search,
+                    metadata_format,
+                    ..Default::default()
+                },
+                no_persist,
+                cancel: Some(install_interrupt_handler()),
+                ..Default::default()
+            };
+
+            let result = pipeline::run_pipeline_with_progress(&url, &opts, |p| match p {
+                pipeline::Progress::Downloading => eprintln!("[1/3] downloading audio..."),
+                pipeline::Progress::Downloaded(path) => {
+                    eprintln!("       saved to {}", path.display())
+                }
+                pipeline::Progress::Transcribing => eprintln!("[2/3] transcribing..."),
+                pipeline::Progress::Transcribed(t) => {
+                    report_language(t, &opts.transcribe);
+                    if verbose {
+                        report_candidates(t);
+                        report_speed(t);
+                    }
+                    eprintln!("       {} segment(s)", t.segments.len());
+                }
+                pipeline::Progress::Storing => eprintln!("[3/3] storing in vector database..."),
+                pipeline::Progress::Stored {
+                    video_id,
+                    count,
+                    offset: Some(offset),
+                } => eprintln!("       appended {count} segment(s) for {video_id} from index {offset}"),
+                pipeline::Progress::Stored { video_id, count, .. } => {
+                    eprintln!("       stored {count} segment(s) for {video_id}")
+                }
+                pipeline::Progress::Warning(msg) => eprintln!("warning: {msg}"),
+            });
+            if let Some(path) = &manifest {
+                let item = pipeline::ManifestItem::from_result(&url, &result);
+                if let Err(e) = pipeline::record_in_manifest(Path::new(path), item) {
+                    eprintln!("warning: could not update manifest {path}: {e}");
+                }
+            }
+
+            let report = match result {
+                Ok(report) => report,
+                Err(pipeline::PipelineError::NoSpeech(wav)) => {
+                    eprintln!("warning: no speech detected in {wav} — nothing stored");
+                    std::process::exit(1);
+                }
+                Err(pipeline::PipelineError::Store(e @ store::StoreError::Interrupted)) => {
+                    eprintln!("{e}");
+                    std::process::exit(130);
+                }
+                Err(pipeline::PipelineError::Download(
+                    e @ downloader::DownloadError::NoAudio(_),
+                )) => {
+                    eprintln!("error: {e}, nothing to transcribe (a slideshow or silent stream?)");
+                    std::process::exit(1);
+                }
+                Err(e @ pipeline::PipelineError::AlreadyStored(_)) => {
+                    eprintln!("error: {e} (use --on-exists overwrite to replace it)");
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            if report.skipped {
+                println!("{} is already stored, skipped", report.video_id);
+                return;
+            }
+
+            let mut summary = comfy_table::Table::new();
+            summary.set_header(["Step", "Duration", "Output"]);
+            summary.add_row([
+                "download".to_string(),
+                format_duration(report.download_time),
+                if report.audio_removed {
+                    format!("{} (removed)", report.wav_path.display())
+                } else {
+                    report.wav_path.display().to_string()
+                },
+            ]);
+            summary.add_row([
+                "transcribe".to_string(),
+                format_duration(report.transcribe_time),
+                format!("{} segment(s)", report.segments_transcribed),
+            ]);
+            summary.add_row([
+                "store".to_string(),
+                format_duration(report.store_time),
+                match report.segments_failed.len() {
+                    0 => format!("{} segment(s) stored", report.segments_stored),
+                    failed => format!(
+                        "{} segment(s) stored, {failed} failed to embed",
+                        report.segments_stored
+                    ),
+                },
+            ]);
+            summary.add_row([
+                "total".to_string(),
+                format_duration(report.total_time()),
+                report.video_id.clone(),
+            ]);
+            eprintln!("{summary}");
+        }
+        Command::PipelineLocal {
+            dir,
+            language,
+            normalize,
+            jobs,
+            detect_window,
+            max_segment_chars,
+            min_segment_duration,
+            preset,
+            suppress_nonspeech,
+            no_suppress_blank,
+            min_confidence,
+            on_exists,
+            tags,
+            store_dir,
+        } => {
+            let (wavs, ignored) = match pipeline::local_wavs(Path::new(&dir)) {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!("error: {dir}: {e}");
+                    return;
+                }
+            };
+            if wavs.is_empty() {
+                eprintln!("error: no .wav files in {dir}");
+                std::process::exit(1);
+            }
+            let opts = pipeline::PipelineOptions {
+                transcribe: transcriber::TranscribeOptions {
+                    language: language.as_deref(),
+                    model_dir,
+                    normalize,
+                    detect_window,
+                    max_segment_chars,
+                    min_segment_duration,
+                    decoding: decoding(preset, suppress_nonspeech, no_suppress_blank),
+                    ..Default::default()
+                },
+                store_dir: &store_dir,
+                min_confidence,
+                on_exists,
+                tags: &tags,
+                ollama_keep_alive: keep_alive,
+                embed_model,
+                no_persist,
+                cancel: Some(install_interrupt_handler()),
+                ..Default::default()
+            };
+
+            eprintln!("transcribing {} WAV file(s) from {dir}...", wavs.len());
+            let (mut stored, mut skipped, mut failed) = (0, 0, 0);
+            let progress = overall_progress(wavs.len(), "files");
+            let result = pipeline::store_local_wavs(&wavs, &opts, jobs, |report| {
+                progress.inc(1);
+                progress.suspend(|| {
+                    let file = report.wav_path.display();
+                    for warning in &report.warnings {
+                        eprintln!("warning: {file}: {warning}");
+                    }
+                    match &report.result {
+                        Ok(_) if report.skipped => {
+                            skipped += 1;
+                            println!("{} is already stored, skipped {file}", report.video_id);
+                        }
+                        Ok(outcome) => {
+                            stored += 1;
+                            println!(
+                                "stored {} segment(s) for {} from {file}",
+                                outcome.stored, report.video_id
+                            );
+                            if !outcome.failed.is_empty() {
+                                eprintln!(
+                                    "warning: {} segment(s) of {} failed to embed and are stored \
+                                     without vectors (retry with `sawtrs verify --retry-failed`)",
+                                    outcome.failed.len(),
+                                    report.video_id
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            eprintln!("error: {file}: {e}");
+                        }
+                    }
+                });
+            });
+            progress.finish_and_clear();
+            match result {
+                Ok(_) => {}
+                Err(pipeline::PipelineError::Store(e @ store::StoreError::Interrupted)) => {
+                    eprintln!("{e} for the current batch; {stored} earlier file(s) stay stored");
+                    std::process::exit(130);
+                }
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            }
+            let total = wavs.len();
+            let mut summary =
+                format!("{stored} of {total} file(s) stored, {skipped} skipped, {failed} failed");
+            if ignored > 0 {
+                summary.push_str(&format!(", {ignored} non-WAV item(s) ignored"));
+            }
+            eprintln!("{summary}");
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Command::Prune {
+            downloads,
+            dry_run,
+            yes,
+        } => {
+            let mut vs = match open_writable(STORE_DIR, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+
+            let downloaded: Vec<String> = std::fs::read_dir(&downloads)
+                .map(|entries| {
+                    entries
+                        .filter_map(Result::ok)
+                        .filter_map(|e| e.file_name().into_string().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let orphaned: Vec<String> = vs
+                .get_video_ids()
+                .into_iter()
+                .filter(|id| !has_wav(&vs, id, &downloaded))
+                .collect();
+
+            if orphaned.is_empty() {
+                println!("no orphaned videos");
+                return;
+            }
+
+            if !dry_run && !yes && !confirm_removal(&vs, &orphaned) {
+                println!("aborted");
+                return;
+            }
+
+            for video_id in &orphaned {
+                if dry_run {
+                    println!("missing WAV: {video_id}");
+                    continue;
+                }
+                match vs.delete_video(video_id) {
+                    Ok(n) => println!("pruned {video_id} ({n} segment(s))"),
+                    Err(e) => eprintln!("error: {video_id}: {e}"),
+                }
+            }
+
+            if dry_run {
+                println!("{} orphaned video(s), run without --dry-run to remove", orphaned.len());
+            }
+        }
+        Command::Delete {
+            video_ids,
+            all,
+            yes,
+        } => {
+            let mut vs = match open_writable(STORE_DIR, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+
+            let video_ids = if all {
+                vs.get_video_ids()
+            } else {
+                match video_ids.iter().map(|id| vs.resolve_video_id(id)).collect() {
+                    Ok(ids) => ids,
+                    Err(e) => {
+                        eprintln!("error: {e}");
+                        return;
+                    }
+                }
+            };
+            if video_ids.is_empty() {
+                println!("store is empty");
+                return;
+            }
+
+            if !yes && !confirm_removal(&vs, &video_ids) {
+                println!("aborted");
+                return;
+            }
+
+            for video_id in &video_ids {
+                match vs.delete_video(video_id) {
+                    Ok(n) => println!("deleted {video_id} ({n} segment(s))"),
+                    Err(e) => eprintln!("error: {video_id}: {e}"),
+                }
+            }
+        }
+        Command::Verify {
+            force_reset: true, ..
+        } => {
+            if no_persist {
+                eprintln!("error: --force-reset moves the index file aside, not with --no-persist");
+                return;
+            }
+            match store::VectorStore::backup_index(STORE_DIR) {
+                Ok(Some(backup)) => println!("moved the index to {}", backup.display()),
+                Ok(None) => println!("no index file found"),
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            }
+            let mut vs = match open_writable(STORE_DIR, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            vs.set_cancel_flag(install_interrupt_handler());
+            if let Some(keep_alive) = keep_alive {
+                vs.set_keep_alive(keep_alive);
+            }
+            if let Some(model) = embed_model {
+                vs.set_embedding_model(model);
+            }
+
+            let result = vs.rebuild_index(|done, total| {
+                eprint!("\rre-embedding {done}/{total}");
+            });
+            match result {
+                Ok(count) => {
+                    if count > 0 {
+                        eprintln!();
+                    }
+                    println!("rebuilt the index from {count} stored segment(s)");
+                }
+                Err(e @ store::StoreError::Interrupted) => {
+                    eprintln!("\n{e}; rerun with --force-reset to finish the rebuild");
+                    std::process::exit(130);
+                }
+                Err(e) => {
+                    eprintln!();
+                    eprintln!("error: {e}");
+                }
+            }
+        }
+        Command::Verify {
+            retry_failed: true, ..
+        } => {
+            let mut vs = match open_writable(STORE_DIR, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            vs.set_cancel_flag(install_interrupt_handler());
+            if let Some(keep_alive) = keep_alive {
+                vs.set_keep_alive(keep_alive);
+            }
+            if let Some(model) = embed_model {
+                vs.set_embedding_model(model);
+            }
+
+            let (mut stored, mut failed) = (0, 0);
+            for id in vs.videos_with_failed_segments() {
+                let indices = vs.failed_segments(&id);
+                match vs.retry_failed(&id, &indices) {
+                    Ok(outcome) => {
+                        println!(
+                            "{id}: {} stored, {} still failing",
+                            outcome.stored,
+                            outcome.failed.len()
+                        );
+                        stored += outcome.stored;
+                        failed += outcome.failed.len();
+                    }
+                    Err(e @ store::StoreError::Interrupted) => {
+                        eprintln!("{e}");
+                        std::process::exit(130);
+                    }
+                    Err(e) => {
+                        eprintln!("error: {id}: {e}");
+                        failed += indices.len();
+                    }
+                }
+            }
+            println!("{stored} segment(s) embedded, {failed} still without a vector");
+        }
+        Command::Verify { repair, .. } => {
+            let mut vs = match open_writable(STORE_DIR, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+
+            let report = if repair { vs.repair() } else { vs.verify() };
+            match report {
+                Ok(report) if report.is_consistent() => println!("store is consistent"),
+                Ok(report) => {
+                    for key in &report.index_only {
+                        println!("index only (no metadata): {key:#018x}");
+                    }
+                    for key in &report.unembedded {
+                        println!("no vector (embedding failed): {key:#018x}");
+                    }
+                    let dangling = report.index_only.len();
+                    if dangling > 0 && repair {
+                        println!("repaired {dangling} dangling key(s)");
+                    } else if dangling > 0 {
+                        println!("{dangling} dangling key(s), run with --repair to remove them");
+                    }
+                    if !report.unembedded.is_empty() {
+                        println!(
+                            "{} segment(s) without a vector, run `sawtrs verify --retry-failed` \
+                             to embed them",
+                            report.unembedded.len()
+                        );
+                    }
+                }
+                Err(e) => eprintln!("error: {e}"),
+            }
+        }
+        Command::Version { store_dir } => {
+            let opts = transcriber::TranscribeOptions { model_dir, ..Default::default() };
+            let whisper_model = match transcriber::model_path(&opts) {
+                Ok(path) => path,
+                Err(e) => format!("{e}"),
+            };
+            let store_model = if Path::new(&store_dir).exists() {
+                match store::VectorStore::open_read_only(&store_dir, Default::default()) {
+                    Ok(vs) => match (vs.embedding_model(), vs.embedding_dim()) {
+                        (Some(model), Some(dim)) => format!("{model} ({dim} dimensions)"),
+                        (Some(model), None) => model.to_string(),
+                        _ => "- (empty store)".to_string(),
+                    },
+                    Err(e) => format!("error: {e}"),
+                }
+            } else {
+                "- (no store)".to_string()
+            };
+
+            let mut table = comfy_table::Table::new();
+            table.add_row(["sawtrs", env!("CARGO_PKG_VERSION")]);
+            table.add_row(["whisper.cpp", transcriber::whisper_version()]);
+            table.add_row(["usearch", store::index_version()]);
+            table.add_row(["Whisper model", &whisper_model]);
+            table.add_row([
+                "Embedding model".to_string(),
+                format!(
+                    "{} ({} dimensions)",
+                    embed_model.unwrap_or(store::EMBEDDING_MODEL),
+                    store::EMBEDDING_DIM
+                ),
+            ]);
+            table.add_row([format!("Store ({store_dir})"), store_model]);
+            println!("{table}");
+        }
+        Command::Reembed { store_dir } => {
+            let mut vs = match open_writable(&store_dir, no_persist) {
+                Ok(vs) => vs,
+                Err(e) => {
+                    eprintln!("error: {e}");
+                    return;
+                }
+            };
+            vs.set_cancel_flag(install_interrupt_handler());
+            if let Some(keep_alive) = keep_alive {
+                vs.set_keep_alive(keep_alive);
+            }
+            if let Some(model) = embed_model {
+                vs.set_embedding_model(model);
+            }
+
+            let result = vs.reembed_with_prefixes(|done, total| {
+                eprint!("\rre-embedding {done}/{total}");
+            });
+            match result {
+                Ok(0) => {
+                    println!("nothing to re-embed (store is empty or already uses task prefixes)")
+                }
+                Ok(count) => {
+                    eprintln!();
+                    println!("re-embedded {count} segment(s) with task prefixes");
+                }
+                Err(e @ store::StoreError::Interrupted) => {
+                    eprintln!("\n{e}");
+                    std::process::exit(130);
+                }
+                Err(e) => {
+                    eprintln!();
+                    eprintln!("error: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Route Ctrl-C to a flag that long store operations poll, so they stop cleanly
+/// without persisting a partial index. A second Ctrl-C exits immediately.
+fn install_interrupt_handler() -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&flag);
+    let result = ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+        eprintln!("\ninterrupting after the current request (Ctrl-C again to quit now)...");
+    });
+    if let Err(e) = result {
+        eprintln!("warning: could not install Ctrl-C handler: {e}");
+    }
+    flag
+}
+
+/// Open the store in `store_dir` for changes; with `--no-persist` nothing is written.
+fn open_writable(
+    store_dir: &str,
+    no_persist: bool,
+) -> Result<store::VectorStore, store::StoreError> {
+    if no_persist {
+        store::VectorStore::open_unpersisted(store_dir, store::IndexTuning::default())
+    } else {
+        store::VectorStore::open(store_dir)
+    }
+}
+
+/// Whether the WAV `video_id` was transcribed from still exists: the path recorded when
+/// it was stored, or a file in the downloads directory (listed in `downloaded`) named
+/// after the video or its source video, whole (`ID.wav`) or clipped (`ID_START_END.wav`).
+fn has_wav(vs: &store::VectorStore, video_id: &str, downloaded: &[String]) -> bool {
+    if let Some(path) = vs.video_info(video_id).and_then(|v| v.wav_path.as_deref())
+        && path.exists()
+    {
+        return true;
+    }
+    let is_clip = |rest: &str| {
+        rest.split_once('_')
+            .is_some_and(|(start, end)| start.parse::<f64>().is_ok() && end.parse::<f64>().is_ok())
+    };
+    [video_id, vs.source_id(video_id)].iter().any(|stem| {
+        downloaded.iter().any(|file| {
+            file.strip_suffix(".wav")
+                .and_then(|name| name.strip_prefix(stem))
+                .is_some_and(|rest| {
+                    rest.is_empty() || rest.strip_prefix('_').is_some_and(is_clip)
+                })
+        })
+    })
+}
+
+/// Show how much data removing `video_ids` would delete and ask the user to confirm.
+/// Anything other than `y`/`yes` (including EOF) declines.
+fn confirm_removal(vs: &store::VectorStore, video_ids: &[String]) -> bool {
+    let segments: usize = video_ids
+        .iter()
+        .map(|id| vs.segment_count(id))
+        .sum();
+    eprint!(
+        "this will remove {} video(s) and {segments} segment(s) from {STORE_DIR}. continue? [y/N] ",
+        video_ids.len()
+    );
+    let _ = std::io::Write::flush(&mut std::io::stderr());
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print a stored video's segments, or write them to `path` (`-` for stdout). Without
+/// an explicit format, files are written in the format their extension implies. A
+/// failed segment lookup is reported instead. With `raw`, segments are written with
+/// whisper's original text where it was recorded.
+fn export_video(
+    segments: Result<Vec<store::StoredSegment>, store::StoreError>,
+    video_id: &str,
+    path: Option<&str>,
+    format: Option<OutputFormat>,
+    order: ExportOrder,
+    raw: bool,
+    opts: export::RenderOptions,
+) {
+    let segments = match segments {
+        Ok(segments) => segments,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return;
+        }
+    };
+
+    let mut export_segs: Vec<export::ExportSegment> = segments
+        .iter()
+        .map(|s| export::ExportSegment {
+            index: s.index,
+            start: s.start,
+            end: s.end,
+            text: match &s.raw_text {
+                Some(raw_text) if raw => raw_text.clone(),
+                _ => s.text.clone(),
+            },
+        })
+        .collect();
+    match order.sort {
+        // get_segments already returns them by start time
+        ExportSort::Start => {}
+        ExportSort::Duration => {
+            export_segs.sort_by(|a, b| (b.end - b.start).total_cmp(&(a.end - a.start)))
+        }
+        ExportSort::Text => export_segs.sort_by_cached_key(|s| s.text.to_lowercase()),
+    }
+    if order.reverse {
+        export_segs.reverse();
+    }
+
+    let list = export::SegmentList {
+        video_id: Some(video_id),
+        file: None,
+        language: None,
+        segments: &export_segs,
+    };
+    let Some(path) = path else {
+        let format = format.unwrap_or(OutputFormat::Table);
+        write_rows("-", format, export::Rows::Segments(list), opts);
+        if format == OutputFormat::Table {
+            println!("{} segment(s)", export_segs.len());
+        }
+        return;
+    };
+    let format = format
+        .or_else(|| OutputFormat::from_path(path))
+        .unwrap_or(OutputFormat::Csv);
+    write_rows(path, format, export::Rows::Segments(list), opts);
+}
+
+/// Overall progress bar on stderr for a loop over `total` items, e.g. `⠋ 3/10 files,
+/// ETA 1m`, with the ETA from the average time per finished item. Hidden for a single
+/// item or when stderr isn't a terminal. Print status lines through `suspend` so they
+/// end up above the bar, and still appear when it is hidden.
+fn overall_progress(total: usize, items: &str) -> indicatif::ProgressBar {
+    if total < 2 || !std::io::stderr().is_terminal() {
+        return indicatif::ProgressBar::hidden();
+    }
+    let style = indicatif::ProgressStyle::with_template("{spinner} {pos}/{len} {msg}, ETA {eta}")
+        .expect("valid progress template");
+    let bar = indicatif::ProgressBar::new(total as u64)
+        .with_style(style)
+        .with_message(items.to_string());
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+/// Directory deleted, with its contents, when dropped.
+struct TempDir(std::path::PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Whether a `transcribe` input is a URL rather than a local path.
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Whisper settings for `--preset` (or the defaults), with `--suppress-nonspeech` on top.
+fn decoding(
+    preset: Option<transcriber::Preset>,
+    suppress_nonspeech: bool,
+    no_suppress_blank: bool,
+) -> transcriber::Decoding {
+    let mut decoding = preset.map(transcriber::Preset::decoding).unwrap_or_default();
+    decoding.suppress_non_speech |= suppress_nonspeech;
+    decoding.suppress_blank &= !no_suppress_blank;
+    decoding
+}
+
+/// Pair up `--start`/`--end` into a clip range. Prints an error and returns `None`
+/// if only one of them was given.
+fn clip_range(start: Option<f64>, end: Option<f64>) -> Option<Option<(f64, f64)>> {
+    match (start, end) {
+        (Some(s), Some(e)) => Some(Some((s, e))),
+        (Some(_), None) | (None, Some(_)) => {
+            eprintln!("error: --start and --end must both be provided");
+            None
+        }
+        (None, None) => Some(None),
+    }
+}
+
+/// Clap value parser for `SS`, `MM:SS` or `H:MM:SS` times.
+fn parse_clock_arg(s: &str) -> Result<f64, String> {
+    parse_clock(s).ok_or_else(|| format!("expected seconds, MM:SS or H:MM:SS, got `{s}`"))
+}
+
+/// Clap value parser for `--duration`: a time as for `parse_clock_arg`, of at least
+/// a millisecond, since whisper reads a zero duration (in ms) as the whole file.
+fn parse_duration_arg(s: &str) -> Result<f64, String> {
+    match parse_clock_arg(s)? {
+        d if (d * 1000.0).round() >= 1.0 => Ok(d),
+        _ => Err(format!("the duration must be above zero, got `{s}`")),
+    }
+}
+
+/// Clap value parser for `pipeline --as`: a non-empty name without whitespace or
+/// path separators, since it also names export files.
+fn parse_store_name(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == '/' || c == '\\') {
+        Err(format!("expected a name without spaces or slashes, got `{s}`"))
+    } else {
+        Ok(s.to_string())
+    }
+}
+
+/// Clap value parser for one `--tags` entry: trimmed, not empty.
+fn parse_tag(s: &str) -> Result<String, String> {
+    match s.trim() {
+        "" => Err("tags can't be empty".to_string()),
+        tag => Ok(tag.to_string()),
+    }
+}
+
+/// Clap value parser for `YYYY-MM-DD` dates.
+fn parse_date(s: &str) -> Result<String, String> {
+    let parts: Vec<&str> = s.split('-').collect();
+    let valid = match parts.as_slice() {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => {
+            let month: u32 = m.parse().unwrap_or(0);
+            let day: u32 = d.parse().unwrap_or(0);
+            y.parse::<u32>().is_ok() && (1..=12).contains(&month) && (1..=31).contains(&day)
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(format!("expected a date as YYYY-MM-DD, got `{s}`"))
+    }
+}
+
+/// Describe a WAV's format and how whisper will take it. Files that can't be read are
+/// skipped here; transcribing them reports the error.
+fn report_wav(path: &str) {
+    let Ok(info) = transcriber::inspect_wav(path) else {
+        return;
+    };
+    let kind = if info.float { "float" } else { "PCM" };
+    let verdict = if info.is_optimal() {
+        "optimal format".to_string()
+    } else if info.wrong_rate() {
+        "whisper expects 16000 Hz, convert it first".to_string()
+    } else {
+        format!("{} channels, downmixed to mono", info.channels)
+    };
+    eprintln!(
+        "{path}: {} Hz, {} channel(s), {}-bit {kind}, {}: {verdict}",
+        info.sample_rate,
+        info.channels,
+        info.bits_per_sample,
+        format_clock(info.duration)
+    );
+}
+
+/// Log how fast whisper ran: audio length, wall-clock time and real-time factor.
+fn report_speed(transcript: &transcriber::Transcript) {
+    let rtf = transcript
+        .real_time_factor()
+        .map_or_else(|| "-".to_string(), |rtf| format!("{rtf:.2}"));
+    eprintln!(
+        "transcribed {:.1}s of audio in {:.1}s (RTF {rtf})",
+        transcript.audio_seconds,
+        transcript.elapsed.as_secs_f64()
+    );
+}
+
+/// Tell the user which language was auto-detected (silent when it was given).
+fn report_language(transcript: &transcriber::Transcript, opts: &transcriber::TranscribeOptions) {
+    if opts.language.is_some() {
+        return;
+    }
+    let Some(lang) = &transcript.language else {
+        return;
+    };
+    match opts.detect_window {
+        Some(secs) => eprintln!("detected language: {lang} (from first {secs}s)"),
+        None => eprintln!("language: {lang}"),
+    }
+}
+
+/// Print the runner-up languages from detection, for deciding whether to force
+/// `--language` on ambiguous or bilingual audio.
+fn report_candidates(transcript: &transcriber::Transcript) {
+    if transcript.language_candidates.is_empty() {
+        return;
+    }
+    let candidates: Vec<String> = transcript
+        .language_candidates
+        .iter()
+        .map(|c| format!("{} {:.1}%", c.code, c.probability * 100.0))
+        .collect();
+    eprintln!("language candidates: {}", candidates.join(", "));
+}
+
+/// Render `rows` to `path` (`-` for stdout), reporting where a file was written.
+/// Tables on stdout wrap to the terminal width; `bom` prefixes CSV with a UTF-8 BOM.
+fn write_rows(
+    path: &str,
+    format: OutputFormat,
+    rows: export::Rows,
+    opts: export::RenderOptions,
+) {
+    let opts = export::RenderOptions {
+        table_width: if path == "-" { terminal_width() } else { None },
+        ..opts
+    };
+    match export::render_to(path, format, rows, opts) {
+        Ok(()) if path == "-" => {}
+        Ok(()) if opts.append => println!("appended to {path}"),
+        Ok(()) => println!("written to {path}"),
+        Err(e) => eprintln!("export error: {e}"),
+    }
+}
+
+/// Search every store and merge the hits into a single ranking by distance. The query
+/// is embedded once per `task_prefixes` setting among the stores, since prefixed and
+/// unprefixed stores need differently embedded queries. Each result is paired with
+/// the index of its store.
+fn search_stores(
+    stores: &[store::VectorStore],
+    store_dirs: &[String],
+    query: &str,
+    n: usize,
+    filter: store::SearchFilter,
+    debug: bool,
+) -> Result<Vec<(usize, store::SearchResult)>, store::StoreError> {
+    if stores.iter().all(store::VectorStore::is_empty) {
+        return Ok(Vec::new());
+    }
+    let stored: usize = stores.iter().map(store::VectorStore::len).sum();
+    if n > stored {
+        eprintln!("warning: only {stored} segment(s) stored, returning all");
+    }
+    let mut query_vecs: [Option<Vec<f32>>; 2] = [None, None];
+
+    let mut merged = Vec::new();
+    for (i, vs) in stores.iter().enumerate() {
+        if vs.is_empty() {
+            continue;
+        }
+        let query_vec = match &mut query_vecs[usize::from(vs.task_prefixes())] {
+            Some(query_vec) => query_vec,
+            slot => slot.insert(vs.embed_query(query)?),
+        };
+        let hits = vs.search_keys_by_vector(query_vec, n, filter)?;
+        if debug {
+            if stores.len() > 1 {
+                eprintln!("[debug] store {}", store_dirs[i]);
+            }
+            print_key_debug(&hits);
+        }
+        merged.extend(
+            hits.into_iter()
+                .filter_map(store::KeyMatch::into_result)
+                .map(|r| (i, r)),
+        );
+    }
+
+    merged.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+    merged.truncate(n);
+    Ok(merged)
+}
+
+/// Print why each hit matched: query words found in its text, and the segments
+/// around it for context.
+fn print_explanations(
+    stores: &[store::VectorStore],
+    query: &str,
+    results: &[(usize, store::SearchResult)],
+) {
+    for (i, (source, r)) in results.iter().enumerate() {
+        let vs = &stores[*source];
+        println!();
+        println!(
+            "#{} {} {}-{}",
+            i + 1,
+            r.video_id,
+            format_clock(r.start),
+            format_clock(r.end)
+        );
+        let terms = overlap_terms(query, &r.text);
+        if terms.is_empty() {
+            println!("  matched terms: none (semantic match only)");
+        } else {
+            println!("  matched terms: {}", terms.join(", "));
+        }
+
+        let before = r.index.checked_sub(1).and_then(|i| vs.segment_at(&r.video_id, i));
+        let after = vs.segment_at(&r.video_id, r.index + 1);
+        if let Some(seg) = before {
+            println!("    {}  {}", format_clock(seg.start), seg.text);
+        }
+        println!("  > {}  {}", format_clock(r.start), r.text);
+        if let Some(seg) = after {
+            println!("    {}  {}", format_clock(seg.start), seg.text);
+        }
+    }
+}
+
+/// Whether to emit ANSI colors: stdout is a terminal, `NO_COLOR` is unset or empty,
+/// and `--no-color` wasn't passed.
+fn use_color(no_color: bool) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !no_color && !no_color_env && std::io::stdout().is_terminal()
+}
+
+/// Width to wrap tables to. Output that isn't going to a terminal keeps its natural
+/// width so it can be piped without line breaks mid-text.
+fn terminal_width() -> Option<u16> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width)
+}
+
+/// Bold/yellow every word of `text` whose lowercase form is in `terms`.
+fn highlight_terms(text: &str, terms: &[String]) -> String {
+    if terms.is_empty() {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut word_start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (c.is_alphanumeric(), word_start) {
+            (true, None) => word_start = Some(i),
+            (false, Some(start)) => {
+                let word = &text[start..i];
+                if terms.contains(&word.to_lowercase()) {
+                    out.push_str("\x1b[1;33m");
+                    out.push_str(word);
+                    out.push_str("\x1b[0m");
+                } else {
+                    out.push_str(word);
+                }
+                word_start = None;
+            }
+            _ => {}
+        }
+        if !c.is_alphanumeric() && i < text.len() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Lowercased query words (3+ characters) that also appear in `text`, in query order.
+fn overlap_terms(query: &str, text: &str) -> Vec<String> {
+    let tokenize = |s: &str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.chars().count() >= 3)
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let text_words: std::collections::HashSet<String> = tokenize(text).into_iter().collect();
+
+    let mut terms: Vec<String> = Vec::new();
+    for word in tokenize(query) {
+        if text_words.contains(&word) && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// Resolve the search query from the positional argument, stdin (`-`), or a file.
+fn resolve_query(query: Option<&str>, query_file: Option<&str>) -> std::io::Result<String> {
+    let raw = match (query, query_file) {
+        (_, Some(path)) => std::fs::read_to_string(path)?,
+        (Some("-"), None) => std::io::read_to_string(std::io::stdin())?,
+        (Some(q), None) => q.to_string(),
+        (None, None) => String::new(),
+    };
+    Ok(raw.trim().to_string())
+}
+
+/// Print each raw index hit to stderr, flagging keys missing from metadata.
+fn print_key_debug(hits: &[store::KeyMatch]) {
+    for hit in hits {
+        match &hit.segment {
+            Some(seg) => eprintln!(
+                "[debug] key={:#018x} distance={:.4} metadata=hit ({} #{})",
+                hit.key, hit.distance, seg.video_id, seg.index
+            ),
+            None => eprintln!(
+                "[debug] key={:#018x} distance={:.4} metadata=MISS",
+                hit.key, hit.distance
+            ),
+        }
+    }
+    let misses = hits.iter().filter(|h| h.segment.is_none()).count();
+    if misses > 0 {
+        eprintln!("[debug] {misses} key(s) in the index have no metadata; index and metadata are out of sync");
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs_f64();
+    if secs < 60.0 {
+        format!("{secs:.1}s")
+    } else {
+        format!("{}m {:04.1}s", (secs / 60.0) as u64, secs % 60.0)
+    }
 }
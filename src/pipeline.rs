@@ -0,0 +1,747 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::downloader::{self, DownloadError};
+use crate::store::{self, IndexTuning, StoreError, StoreOutcome, VectorStore};
+use crate::transcriber::{self, Segment, TranscribeError, TranscribeOptions, Transcript};
+
+pub const DEFAULT_DOWNLOADS_DIR: &str = "downloads";
+pub const DEFAULT_STORE_DIR: &str = "store_data";
+/// Segments buffered before each store write in streaming mode.
+const STREAM_FLUSH_SEGMENTS: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineError {
+    #[error(transparent)]
+    Download(#[from] DownloadError),
+    #[error(transparent)]
+    Transcribe(#[from] TranscribeError),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+    #[error("no speech detected in {0}")]
+    NoSpeech(String),
+    #[error("{0} is already stored")]
+    AlreadyStored(String),
+}
+
+/// What to do when the video is already in the store (without `append`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnExists {
+    /// Replace its stored transcript.
+    #[default]
+    Overwrite,
+    /// Leave it as it is, without downloading or transcribing anything.
+    Skip,
+    /// Fail with `PipelineError::AlreadyStored`.
+    Error,
+}
+
+/// Options for a full download → transcribe → store run.
+#[derive(Debug, Clone)]
+pub struct PipelineOptions<'a> {
+    pub downloads_dir: &'a str,
+    pub store_dir: &'a str,
+    pub transcribe: TranscribeOptions<'a>,
+    /// Download only `(start_secs, end_secs)`; timestamps are offset to match the video.
+    pub clip: Option<(f64, f64)>,
+    /// Always ask yt-dlp for the canonical video ID instead of parsing the URL.
+    pub resolve_id: bool,
+    /// Download retries, see `DownloadOptions::retries`.
+    pub retries: Option<u32>,
+    /// Store the transcript under this name instead of the video ID, e.g. to keep a
+    /// clip next to the full video. The real ID is recorded as the video's `source_id`,
+    /// and the WAV is given this name as well (`DownloadOptions::name`).
+    pub store_as: Option<&'a str>,
+    /// Download again even if the WAV exists, replacing a file of the same name that
+    /// holds another video's audio; see `DownloadOptions::force`.
+    pub force_download: bool,
+    /// Delete the downloaded WAV once its segments are stored. The audio is kept if
+    /// storing fails or stores nothing.
+    pub cleanup: bool,
+    /// Skip segments whose confidence is below this value.
+    pub min_confidence: Option<f32>,
+    /// Continue after the video's stored segments instead of overwriting them.
+    pub append: bool,
+    /// What to do when the video is already stored; ignored when appending.
+    pub on_exists: OnExists,
+    /// Tags recorded for the video. Empty keeps the tags it was stored with before.
+    pub tags: &'a [String],
+    /// Embed and store segments in chunks while whisper is still decoding, so a long
+    /// video becomes searchable as it goes. Streamed segments carry no confidence,
+    /// so `min_confidence` has no effect, and chunks stored before an error or an
+    /// interruption are kept.
+    pub stream: bool,
+    /// How long Ollama keeps the embedding model loaded, e.g. `10m`; see
+    /// `Embedder::set_keep_alive`.
+    pub ollama_keep_alive: Option<&'a str>,
+    /// Ollama embedding model; storing fails if the store was embedded with another.
+    pub embed_model: Option<&'a str>,
+    /// HNSW overrides used when opening the store.
+    pub tuning: IndexTuning,
+    /// Keep the store's changes in memory only, see `VectorStore::open_unpersisted`. The
+    /// downloaded WAV is then kept even with `cleanup`, as nothing else survives the run.
+    pub no_persist: bool,
+    /// Once set, the run stops at the next step boundary or embedding batch with
+    /// `StoreError::Interrupted`, before anything is written to the store.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for PipelineOptions<'_> {
+    fn default() -> Self {
+        Self {
+            downloads_dir: DEFAULT_DOWNLOADS_DIR,
+            store_dir: DEFAULT_STORE_DIR,
+            transcribe: TranscribeOptions::default(),
+            clip: None,
+            resolve_id: false,
+            retries: None,
+            store_as: None,
+            force_download: false,
+            cleanup: false,
+            min_confidence: None,
+            append: false,
+            on_exists: OnExists::Overwrite,
+            tags: &[],
+            stream: false,
+            ollama_keep_alive: None,
+            embed_model: None,
+            tuning: IndexTuning::default(),
+            no_persist: false,
+            cancel: None,
+        }
+    }
+}
+
+/// Progress events emitted while the pipeline runs.
+#[derive(Debug)]
+pub enum Progress<'a> {
+    Downloading,
+    Downloaded(&'a Path),
+    Transcribing,
+    Transcribed(&'a Transcript),
+    Storing,
+    /// `offset` is the first index used in append mode.
+    Stored {
+        video_id: &'a str,
+        count: usize,
+        offset: Option<usize>,
+    },
+    /// A non-fatal problem, e.g. the metadata lookup failed.
+    Warning(&'a str),
+}
+
+/// What a pipeline run produced and how long each step took.
+#[derive(Debug, Clone)]
+pub struct PipelineReport {
+    /// Name the transcript was stored under (the video ID unless `store_as` was set).
+    pub video_id: String,
+    pub wav_path: PathBuf,
+    /// Whether `wav_path` was deleted after storing (`cleanup`).
+    pub audio_removed: bool,
+    pub language: Option<String>,
+    pub segments_transcribed: usize,
+    pub segments_stored: usize,
+    /// Indices stored without a vector because embedding failed; see
+    /// `VectorStore::retry_failed`.
+    pub segments_failed: Vec<usize>,
+    /// First index used when appending; `None` for a normal (overwriting) store.
+    pub index_offset: Option<usize>,
+    /// The video was already stored and `OnExists::Skip` left it alone; nothing was
+    /// downloaded, transcribed or stored.
+    pub skipped: bool,
+    pub download_time: Duration,
+    pub transcribe_time: Duration,
+    pub store_time: Duration,
+    pub warnings: Vec<String>,
+}
+
+impl PipelineReport {
+    pub fn total_time(&self) -> Duration {
+        self.download_time + self.transcribe_time + self.store_time
+    }
+
+    fn skipped(video_id: &str) -> Self {
+        Self {
+            video_id: video_id.to_string(),
+            wav_path: PathBuf::new(),
+            audio_removed: false,
+            language: None,
+            segments_transcribed: 0,
+            segments_stored: 0,
+            segments_failed: Vec::new(),
+            index_offset: None,
+            skipped: true,
+            download_time: Duration::ZERO,
+            transcribe_time: Duration::ZERO,
+            store_time: Duration::ZERO,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Download, transcribe and store a video in one call.
+pub fn run_pipeline(url: &str, opts: &PipelineOptions) -> Result<PipelineReport, PipelineError> {
+    run_pipeline_with_progress(url, opts, |_| {})
+}
+
+/// Like `run_pipeline`, calling `on_progress` as each step starts and finishes.
+pub fn run_pipeline_with_progress<F>(
+    url: &str,
+    opts: &PipelineOptions,
+    mut on_progress: F,
+) -> Result<PipelineReport, PipelineError>
+where
+    F: FnMut(Progress),
+{
+    let video_id = downloader::resolve_video_id(url, opts.resolve_id)?;
+    // Once resolved, the canonical ID is what gets downloaded, so file and store keys agree
+    let url = if opts.resolve_id { video_id.as_str() } else { url };
+    let mut warnings = Vec::new();
+    let mut warn = |msg: String, on_progress: &mut F| {
+        on_progress(Progress::Warning(&msg));
+        warnings.push(msg);
+    };
+    let check_cancelled = || match &opts.cancel {
+        Some(flag) if flag.load(Ordering::SeqCst) => Err(StoreError::Interrupted),
+        _ => Ok(()),
+    };
+
+    let store_id = opts.store_as.unwrap_or(&video_id);
+    if !opts.append
+        && VectorStore::open_read_only(opts.store_dir, opts.tuning)?.has_video(store_id)
+    {
+        match opts.on_exists {
+            OnExists::Overwrite => {}
+            OnExists::Skip => return Ok(PipelineReport::skipped(store_id)),
+            OnExists::Error => return Err(PipelineError::AlreadyStored(store_id.to_string())),
+        }
+    }
+
+    // Step 1: Download
+    on_progress(Progress::Downloading);
+    let step_started = Instant::now();
+    let download_opts = downloader::DownloadOptions {
+        clip: opts.clip,
+        write_info_json: true,
+        retries: opts.retries,
+        name: opts.store_as.map(str::to_string),
+        force: opts.force_download,
+        ..Default::default()
+    };
+    let audio = downloader::download(url, opts.downloads_dir, &download_opts)?;
+    let wav_path = audio.path;
+    on_progress(Progress::Downloaded(&wav_path));
+
+    // Prefer the sidecar yt-dlp wrote during the download; query it again only if missing
+    let from_sidecar = audio
+        .info_json
+        .as_deref()
+        .map(downloader::read_info_json)
+        .and_then(Result::ok);
+    let metadata = match from_sidecar.map_or_else(|| downloader::fetch_metadata(url), Ok) {
+        Ok(meta) => Some(meta),
+        Err(e) => {
+            warn(format!("could not fetch video metadata: {e}"), &mut on_progress);
+            None
+        }
+    };
+    let download_time = step_started.elapsed();
+
+    check_cancelled()?;
+
+    // Step 2: Transcribe (and, when streaming, store as segments arrive)
+    on_progress(Progress::Transcribing);
+    let step_started = Instant::now();
+    let transcribe_opts = TranscribeOptions {
+        offset_seconds: opts.transcribe.offset_seconds + audio.offset_seconds,
+        ..opts.transcribe.clone()
+    };
+    let wav = wav_path.to_str().unwrap_or_default();
+    // The writable store is opened as late as possible: it holds the writer lock, so
+    // other processes can't store anything until it is dropped
+    let mut streaming_store = if opts.stream { Some(open_store(opts)?) } else { None };
+    let mut streamed = None;
+    let transcript = if let Some(vs) = streaming_store.as_mut() {
+        let first_index = if opts.append { vs.next_index(store_id) } else { 0 };
+        let mut sink = StreamSink::new(vs, store_id, opts.min_confidence, first_index);
+        let transcript = transcriber::transcribe_streaming(wav, &transcribe_opts, |seg| {
+            sink.push(seg)
+        })?;
+        let (outcome, store_time) = sink.finish()?;
+        streamed = Some((first_index, outcome, store_time));
+        transcript
+    } else {
+        transcriber::transcribe(wav, &transcribe_opts)?
+    };
+    if !transcript.has_speech() {
+        return Err(PipelineError::NoSpeech(wav_path.display().to_string()));
+    }
+    if let Some(w) = &transcript.input_warning {
+        warn(w.clone(), &mut on_progress);
+    }
+    if let Some(w) = &transcript.warning {
+        warn(format!("partial transcript: {w}"), &mut on_progress);
+    }
+    on_progress(Progress::Transcribed(&transcript));
+    // Streaming time spent storing is reported as the store step
+    let transcribe_time = step_started
+        .elapsed()
+        .saturating_sub(streamed.as_ref().map_or(Duration::ZERO, |(_, _, time)| *time));
+
+    if streamed.is_none() {
+        check_cancelled()?;
+    }
+    let mut vs = match streaming_store {
+        Some(vs) => vs,
+        None => open_store(opts)?,
+    };
+
+    // Fields yt-dlp (or whisper) didn't report this time keep their stored values
+    let previous = vs.video_info(store_id).cloned().unwrap_or_default();
+    let meta = metadata.unwrap_or_default();
+    let info = store::VideoInfo {
+        title: meta.title.or(previous.title),
+        channel: meta.channel.or(previous.channel),
+        duration: meta.duration.or(previous.duration),
+        upload_date: meta.upload_date.or(previous.upload_date),
+        language: transcript.language.clone().or(previous.language),
+        source_id: opts.store_as.is_some().then(|| video_id.clone()).or(previous.source_id),
+        tags: if opts.tags.is_empty() { previous.tags } else { opts.tags.to_vec() },
+        wav_path: Some(wav_path.clone()),
+    };
+    if let Err(e) = vs.set_video_info(store_id, info) {
+        warn(format!("could not save video metadata: {e}"), &mut on_progress);
+    }
+
+    // Step 3: Store (already done when streaming)
+    let (index_offset, outcome, store_time) = match streamed {
+        Some((first_index, outcome, store_time)) => {
+            // The chunks only overwrote indices from 0; drop what a longer earlier
+            // transcript left beyond them
+            if !opts.append {
+                vs.truncate_video(store_id, outcome.stored + outcome.failed.len())?;
+            }
+            (opts.append.then_some(first_index), outcome, store_time)
+        }
+        None => {
+            on_progress(Progress::Storing);
+            let step_started = Instant::now();
+            let store_segments: Vec<store::TranscriptSegment> =
+                transcript.segments.iter().map(to_store_segment).collect();
+            let (index_offset, outcome) = if opts.append {
+                let (offset, outcome) =
+                    vs.store_transcript_append(store_id, &store_segments, opts.min_confidence)?;
+                (Some(offset), outcome)
+            } else {
+                let outcome =
+                    vs.store_transcript(store_id, &store_segments, opts.min_confidence)?;
+                (None, outcome)
+            };
+            (index_offset, outcome, step_started.elapsed())
+        }
+    };
+    let stored = outcome.stored;
+    on_progress(Progress::Stored {
+        video_id: store_id,
+        count: stored,
+        offset: index_offset,
+    });
+    if !outcome.failed.is_empty() {
+        warn(
+            format!(
+                "{} segment(s) of {store_id} failed to embed and are stored without vectors \
+                 (retry with `sawtrs verify --retry-failed`)",
+                outcome.failed.len()
+            ),
+            &mut on_progress,
+        );
+    }
+
+    let mut audio_removed = false;
+    if stored == 0 {
+        warn(
+            format!("every segment was filtered out — nothing stored for {store_id}"),
+            &mut on_progress,
+        );
+    } else if opts.cleanup && !opts.no_persist {
+        match std::fs::remove_file(&wav_path) {
+            Ok(()) => audio_removed = true,
+            Err(e) => warn(
+                format!("could not remove {}: {e}", wav_path.display()),
+                &mut on_progress,
+            ),
+        }
+    }
+
+    Ok(PipelineReport {
+        video_id: store_id.to_string(),
+        wav_path,
+        audio_removed,
+        language: transcript.language,
+        segments_transcribed: transcript.segments.len(),
+        segments_stored: stored,
+        segments_failed: outcome.failed,
+        index_offset,
+        skipped: false,
+        download_time,
+        transcribe_time,
+        store_time,
+        warnings,
+    })
+}
+
+/// What `store_local_wavs` did with one WAV file.
+#[derive(Debug)]
+pub struct LocalFileReport {
+    pub wav_path: PathBuf,
+    /// Name the transcript was stored under, see `local_video_id`.
+    pub video_id: String,
+    /// Segments stored (and failed to embed), or why the file wasn't stored.
+    pub result: Result<StoreOutcome, PipelineError>,
+    /// The file was already stored and `OnExists::Skip` left it alone.
+    pub skipped: bool,
+    pub warnings: Vec<String>,
+}
+
+/// The `.wav` files directly inside `dir` (any case of the extension), sorted, and
+/// the number of other entries that were ignored, subdirectories included.
+pub fn local_wavs(dir: &Path) -> std::io::Result<(Vec<PathBuf>, usize)> {
+    let mut wavs = Vec::new();
+    let mut ignored = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_wav = path.is_file()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+        if is_wav {
+            wavs.push(path);
+        } else {
+            ignored += 1;
+        }
+    }
+    wavs.sort();
+    Ok((wavs, ignored))
+}
+
+/// Name a local WAV is stored under: its file stem, with whitespace and backslashes
+/// replaced by `_` so it can be passed to `export`, `delete` etc. unquoted.
+pub fn local_video_id(wav: &Path) -> String {
+    wav.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(|c: char| c.is_whitespace() || c == '\\', "_")
+}
+
+/// Transcribe local WAV files and store each under `local_video_id`, the counterpart
+/// of `run_pipeline` for recordings already on disk.
+///
+/// Files go through in batches of `jobs` (clamped to `1..=MAX_JOBS`): a batch is
+/// transcribed concurrently with one model loaded for the whole run, then stored,
+/// and `on_file` gets each file's report as soon as it is final. Of `opts`, only the
+/// store, transcription, tag, `min_confidence`, `on_exists` and embedding settings
+/// apply. The store is opened for writing only to store a batch, so a long run
+/// doesn't hold the writer lock while transcribing; `on_exists` is checked
+/// against the store as it is when the batch starts.
+///
+/// A file that fails is reported in its entry without stopping the others. Opening
+/// the store, loading the model and an interruption (checked between batches) fail
+/// the whole run; every file reported to `on_file` until then stays stored. Reports
+/// are in the order of `wavs`.
+pub fn store_local_wavs<F>(
+    wavs: &[PathBuf],
+    opts: &PipelineOptions,
+    jobs: usize,
+    mut on_file: F,
+) -> Result<Vec<LocalFileReport>, PipelineError>
+where
+    F: FnMut(&LocalFileReport),
+{
+    let check_cancelled = || match &opts.cancel {
+        Some(flag) if flag.load(Ordering::SeqCst) => Err(StoreError::Interrupted),
+        _ => Ok(()),
+    };
+    let report = |wav: &Path, video_id: String, result| LocalFileReport {
+        wav_path: wav.to_path_buf(),
+        video_id,
+        result,
+        skipped: false,
+        warnings: Vec::new(),
+    };
+
+    let mut model = None;
+    let mut reports = Vec::with_capacity(wavs.len());
+    for batch in wavs.chunks(jobs.clamp(1, transcriber::MAX_JOBS)) {
+        check_cancelled()?;
+        let stored = VectorStore::open_read_only(opts.store_dir, opts.tuning)?;
+        let mut done = Vec::with_capacity(batch.len());
+        let mut pending = Vec::new();
+        for (i, wav) in batch.iter().enumerate() {
+            let video_id = local_video_id(wav);
+            match opts.on_exists {
+                OnExists::Skip if stored.has_video(&video_id) => done.push((
+                    i,
+                    LocalFileReport {
+                        skipped: true,
+                        ..report(wav, video_id, Ok(StoreOutcome::default()))
+                    },
+                )),
+                OnExists::Error if stored.has_video(&video_id) => {
+                    let error = PipelineError::AlreadyStored(video_id.clone());
+                    done.push((i, report(wav, video_id, Err(error))));
+                }
+                _ => pending.push((i, wav, video_id)),
+            }
+        }
+        drop(stored);
+
+        if !pending.is_empty() {
+            let model = match &mut model {
+                Some(model) => model,
+                None => model.insert(transcriber::Model::load(&opts.transcribe)?),
+            };
+            let files: Vec<String> =
+                pending.iter().map(|(_, wav, _)| wav.display().to_string()).collect();
+            let transcripts = model.transcribe_many(&files, &opts.transcribe, jobs);
+            check_cancelled()?;
+
+            let mut vs = open_store(opts)?;
+            for ((i, wav, video_id), transcript) in pending.into_iter().zip(transcripts) {
+                let mut entry = report(wav, video_id, Ok(StoreOutcome::default()));
+                entry.result = match transcript {
+                    Ok(transcript) => store_local(&mut vs, &mut entry, transcript, opts),
+                    Err(e) => Err(e.into()),
+                };
+                if let Err(PipelineError::Store(StoreError::Interrupted)) = entry.result {
+                    return Err(StoreError::Interrupted.into());
+                }
+                done.push((i, entry));
+            }
+        }
+
+        done.sort_by_key(|(i, _)| *i);
+        for (_, entry) in done {
+            on_file(&entry);
+            reports.push(entry);
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Store one transcript for `store_local_wavs`, keeping the video's previous info
+/// apart from its language and (if given) tags.
+fn store_local(
+    vs: &mut VectorStore,
+    entry: &mut LocalFileReport,
+    transcript: Transcript,
+    opts: &PipelineOptions,
+) -> Result<StoreOutcome, PipelineError> {
+    if !transcript.has_speech() {
+        return Err(PipelineError::NoSpeech(entry.wav_path.display().to_string()));
+    }
+    if let Some(w) = &transcript.input_warning {
+        entry.warnings.push(w.clone());
+    }
+    if let Some(w) = &transcript.warning {
+        entry.warnings.push(format!("partial transcript: {w}"));
+    }
+    let segments: Vec<store::TranscriptSegment> =
+        transcript.segments.iter().map(to_store_segment).collect();
+    let outcome = vs.store_transcript(&entry.video_id, &segments, opts.min_confidence)?;
+    if outcome.stored == 0 {
+        entry.warnings.push("every segment was filtered out, nothing stored".to_string());
+    }
+
+    let previous = vs.video_info(&entry.video_id).cloned().unwrap_or_default();
+    let info = store::VideoInfo {
+        language: transcript.language,
+        tags: if opts.tags.is_empty() { previous.tags.clone() } else { opts.tags.to_vec() },
+        wav_path: Some(entry.wav_path.clone()),
+        ..previous
+    };
+    if let Err(e) = vs.set_video_info(&entry.video_id, info) {
+        entry.warnings.push(format!("could not save video metadata: {e}"));
+    }
+    Ok(outcome)
+}
+
+/// Open the store `opts` names, with its cancel flag and embedding settings applied.
+fn open_store(opts: &PipelineOptions) -> Result<VectorStore, StoreError> {
+    let mut vs = if opts.no_persist {
+        VectorStore::open_unpersisted(opts.store_dir, opts.tuning)?
+    } else {
+        VectorStore::open_with(opts.store_dir, opts.tuning)?
+    };
+    if let Some(flag) = &opts.cancel {
+        vs.set_cancel_flag(Arc::clone(flag));
+    }
+    if let Some(keep_alive) = opts.ollama_keep_alive {
+        vs.set_keep_alive(keep_alive);
+    }
+    if let Some(model) = opts.embed_model {
+        vs.set_embedding_model(model);
+    }
+    Ok(vs)
+}
+
+/// One URL's entry in a run manifest: what the pipeline produced for it, or why it failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestItem {
+    pub url: String,
+    /// Name the transcript was stored under; parsed from the URL when the run failed.
+    pub video_id: Option<String>,
+    pub wav_path: Option<PathBuf>,
+    pub segments_transcribed: usize,
+    pub segments_stored: usize,
+    pub segments_failed: usize,
+    /// The video was already stored and left alone (`OnExists::Skip`).
+    #[serde(default)]
+    pub skipped: bool,
+    pub error: Option<String>,
+}
+
+impl ManifestItem {
+    /// Summarize a `run_pipeline` result for `url`.
+    pub fn from_result(url: &str, result: &Result<PipelineReport, PipelineError>) -> Self {
+        match result {
+            Ok(report) => Self {
+                url: url.to_string(),
+                video_id: Some(report.video_id.clone()),
+                wav_path: (!report.skipped && !report.audio_removed)
+                    .then(|| report.wav_path.clone()),
+                segments_transcribed: report.segments_transcribed,
+                segments_stored: report.segments_stored,
+                segments_failed: report.segments_failed.len(),
+                skipped: report.skipped,
+                error: None,
+            },
+            Err(e) => Self {
+                url: url.to_string(),
+                video_id: downloader::extract_video_id(url).ok(),
+                wav_path: None,
+                segments_transcribed: 0,
+                segments_stored: 0,
+                segments_failed: 0,
+                skipped: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Machine-readable record of pipeline runs, kept in a JSON file across runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub items: Vec<ManifestItem>,
+}
+
+/// Add `item` to the manifest at `path`, replacing the previous entry for its URL, so
+/// the file holds the latest outcome per URL and can be diffed between runs. A missing
+/// file is created.
+pub fn record_in_manifest(path: &Path, item: ManifestItem) -> std::io::Result<()> {
+    let mut manifest: Manifest = match std::fs::read_to_string(path) {
+        Ok(data) => serde_json::from_str(&data)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+        Err(e) => return Err(e),
+    };
+    match manifest.items.iter_mut().find(|i| i.url == item.url) {
+        Some(existing) => *existing = item,
+        None => manifest.items.push(item),
+    }
+    let mut data = serde_json::to_string_pretty(&manifest)?;
+    data.push('\n');
+    std::fs::write(path, data)
+}
+
+fn to_store_segment(s: &Segment) -> store::TranscriptSegment {
+    store::TranscriptSegment {
+        start: s.start,
+        end: s.end,
+        text: s.text.clone(),
+        confidence: s.confidence,
+        raw_text: s.raw_text.clone(),
+    }
+}
+
+/// Stores segments in chunks of `STREAM_FLUSH_SEGMENTS` as whisper produces them
+/// (`PipelineOptions::stream`). After the first error nothing more is stored, and
+/// `finish` returns it.
+struct StreamSink<'s> {
+    vs: &'s mut VectorStore,
+    video_id: &'s str,
+    min_confidence: Option<f32>,
+    next_index: usize,
+    pending: Vec<store::TranscriptSegment>,
+    outcome: StoreOutcome,
+    store_time: Duration,
+    error: Option<StoreError>,
+}
+
+impl<'s> StreamSink<'s> {
+    fn new(
+        vs: &'s mut VectorStore,
+        video_id: &'s str,
+        min_confidence: Option<f32>,
+        first_index: usize,
+    ) -> Self {
+        Self {
+            vs,
+            video_id,
+            min_confidence,
+            next_index: first_index,
+            pending: Vec::with_capacity(STREAM_FLUSH_SEGMENTS),
+            outcome: StoreOutcome::default(),
+            store_time: Duration::ZERO,
+            error: None,
+        }
+    }
+
+    fn push(&mut self, segment: &Segment) {
+        if self.error.is_some() {
+            return;
+        }
+        self.pending.push(to_store_segment(segment));
+        if self.pending.len() >= STREAM_FLUSH_SEGMENTS {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() || self.error.is_some() {
+            return;
+        }
+        let started = Instant::now();
+        let result = self.vs.store_transcript_at(
+            self.video_id,
+            &self.pending,
+            self.min_confidence,
+            self.next_index,
+        );
+        match result {
+            Ok(outcome) => {
+                // Blank segments aren't stored and don't use up an index
+                self.next_index += outcome.stored + outcome.failed.len();
+                self.outcome.stored += outcome.stored;
+                self.outcome.failed.extend(outcome.failed);
+            }
+            Err(e) => self.error = Some(e),
+        }
+        self.pending.clear();
+        self.store_time += started.elapsed();
+    }
+
+    /// Store what is left and return the totals, or the first error.
+    fn finish(mut self) -> Result<(StoreOutcome, Duration), StoreError> {
+        self.flush();
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok((self.outcome, self.store_time)),
+        }
+    }
+}
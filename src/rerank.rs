@@ -0,0 +1,184 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+const OLLAMA_GENERATE_URL: &str = "http://localhost:11434/api/generate";
+/// Ollama model `OllamaReranker` asks unless another one is given.
+pub const DEFAULT_RERANK_MODEL: &str = "llama3.2";
+/// Highest relevance score the model is asked for.
+const MAX_SCORE: f32 = 10.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RerankError {
+    #[error("Ollama is not running at {OLLAMA_GENERATE_URL}. Start it with: ollama serve")]
+    OllamaUnavailable,
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("reranking failed: {0}")]
+    Failed(String),
+    #[error("interrupted, results not reranked")]
+    Interrupted,
+}
+
+/// Second pass over the nearest vector hits, reordering them by how well each text
+/// answers the query. Vector distance is quick but coarse; a reranker reads query
+/// and text together, which is slower and more precise.
+pub trait Reranker {
+    /// Indices into `texts` (nearest hit first), best match first. Every index
+    /// appears exactly once; equally good texts keep their vector order.
+    fn rerank(&self, query: &str, texts: &[&str]) -> Result<Vec<usize>, RerankError>;
+}
+
+/// Keeps the vector order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoRerank;
+
+impl Reranker for NoRerank {
+    fn rerank(&self, _query: &str, texts: &[&str]) -> Result<Vec<usize>, RerankError> {
+        Ok((0..texts.len()).collect())
+    }
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
+    model: &'a str,
+    prompt: String,
+    stream: bool,
+    options: GenerateOptions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct GenerateOptions {
+    temperature: f32,
+}
+
+#[derive(Deserialize)]
+struct GenerateResponse {
+    response: String,
+}
+
+/// Scores each text with an LLM through Ollama's `/api/generate`: the model rates
+/// the text's relevance to the query from 0 to 10, one request per text, and texts
+/// are sorted by that score. Ollama has no cross-encoder endpoint; such a model can
+/// be plugged in by implementing `Reranker` instead.
+pub struct OllamaReranker {
+    client: reqwest::blocking::Client,
+    model: String,
+    keep_alive: Option<serde_json::Value>,
+    /// Sent as the `Authorization` header; never printed.
+    api_key: Option<String>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for OllamaReranker {
+    fn default() -> Self {
+        Self::new(DEFAULT_RERANK_MODEL)
+    }
+}
+
+impl OllamaReranker {
+    /// Rerank with the Ollama model `model`, which must already be pulled.
+    pub fn new(model: &str) -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            model: model.to_string(),
+            keep_alive: None,
+            api_key: store::api_key_from_env(),
+            cancel: None,
+        }
+    }
+
+    /// How long Ollama keeps the model loaded after each request, as for
+    /// `Embedder::set_keep_alive`.
+    pub fn set_keep_alive(&mut self, keep_alive: &str) {
+        self.keep_alive = Some(store::keep_alive_value(keep_alive));
+    }
+
+    /// Authenticate every request with `key` instead of `$OLLAMA_API_KEY`; see
+    /// `Embedder::set_api_key`.
+    pub fn set_api_key(&mut self, key: &str) {
+        self.api_key = Some(key.to_string());
+    }
+
+    /// Stop with `RerankError::Interrupted` once `flag` is set, checked between texts.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
+
+    /// Ask the model how relevant `text` is to `query`, from 0 to `MAX_SCORE`.
+    fn score(&self, query: &str, text: &str) -> Result<f32, RerankError> {
+        let body = GenerateRequest {
+            model: &self.model,
+            prompt: format!(
+                "Rate how relevant the passage is to the search query, from 0 (unrelated) \
+                 to 10 (answers it directly). Reply with the number only.\n\n\
+                 Query: {query}\n\nPassage: {text}\n\nScore:"
+            ),
+            stream: false,
+            options: GenerateOptions { temperature: 0.0 },
+            keep_alive: self.keep_alive.as_ref(),
+        };
+
+        let mut request = self.client.post(OLLAMA_GENERATE_URL).json(&body);
+        if let Some(key) = &self.api_key {
+            let header =
+                store::authorization(key).map_err(|e| RerankError::Failed(e.to_string()))?;
+            request = request.header(reqwest::header::AUTHORIZATION, header);
+        }
+        let resp = request.send().map_err(|e| {
+            if e.is_connect() {
+                RerankError::OllamaUnavailable
+            } else {
+                RerankError::Http(e)
+            }
+        })?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().unwrap_or_default();
+            return Err(RerankError::Failed(format!("HTTP {status}: {body}")));
+        }
+
+        let parsed: GenerateResponse = resp.json()?;
+        parse_score(&parsed.response).ok_or_else(|| {
+            RerankError::Failed(format!(
+                "`{}` answered `{}` instead of a score",
+                self.model,
+                parsed.response.trim()
+            ))
+        })
+    }
+}
+
+impl Reranker for OllamaReranker {
+    fn rerank(&self, query: &str, texts: &[&str]) -> Result<Vec<usize>, RerankError> {
+        let mut scored = Vec::with_capacity(texts.len());
+        for (i, text) in texts.iter().enumerate() {
+            if let Some(flag) = &self.cancel
+                && flag.load(Ordering::SeqCst)
+            {
+                return Err(RerankError::Interrupted);
+            }
+            scored.push((i, self.score(query, text)?));
+        }
+        // Stable, so ties stay in vector order
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored.into_iter().map(|(i, _)| i).collect())
+    }
+}
+
+/// The first number in a model's answer, clamped to `0..=MAX_SCORE`.
+fn parse_score(answer: &str) -> Option<f32> {
+    let start = answer.find(|c: char| c.is_ascii_digit())?;
+    let number: String = answer[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let score: f32 = number.trim_end_matches('.').parse().ok()?;
+    Some(score.clamp(0.0, MAX_SCORE))
+}
@@ -1,15 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 use usearch::{Index, IndexOptions, MetricKind, ScalarKind};
 
-const EMBEDDING_DIM: usize = 768;
+/// Vector size of the index, matching `nomic-embed-text`.
+pub const EMBEDDING_DIM: usize = 768;
 const OLLAMA_EMBED_URL: &str = "http://localhost:11434/api/embed";
-const EMBEDDING_MODEL: &str = "nomic-embed-text";
+/// Ollama model texts are embedded with unless `Embedder::set_model` picks another.
+pub const EMBEDDING_MODEL: &str = "nomic-embed-text";
+/// Environment variable with the credentials sent to Ollama, for instances behind an
+/// authenticating reverse proxy.
+pub const OLLAMA_API_KEY_ENV_VAR: &str = "OLLAMA_API_KEY";
 const INDEX_FILE: &str = "index.usearch";
 const METADATA_FILE: &str = "metadata.json";
+const METADATA_BINARY_FILE: &str = "metadata.bin";
+/// First bytes of `metadata.bin`, followed by a little-endian u32 format version.
+const BINARY_METADATA_MAGIC: &[u8; 8] = b"SAWTRSMD";
+const BINARY_METADATA_VERSION: u32 = 1;
+const INDEX_CONFIG_FILE: &str = "index_config.json";
+const VIDEOS_FILE: &str = "videos.json";
+/// Locked by the one process that has the store open for writing.
+const LOCK_FILE: &str = "writer.lock";
+/// Texts per Ollama request; the cancel flag is checked between batches.
+const EMBED_BATCH_SIZE: usize = 64;
+/// Query embeddings kept by `Embedder` for repeated searches.
+const QUERY_CACHE_SIZE: usize = 64;
+/// nomic-embed-text task prefixes, used by stores with `IndexConfig::task_prefixes`.
+const DOCUMENT_PREFIX: &str = "search_document: ";
+const QUERY_PREFIX: &str = "search_query: ";
 
 // ── Error type ──────────────────────────────────────────────────────────
 
@@ -19,10 +41,54 @@ pub enum StoreError {
     OllamaUnavailable,
     #[error("embedding failed: {0}")]
     EmbeddingFailed(String),
+    #[error("the Ollama API key contains characters that can't be sent in an HTTP header")]
+    InvalidApiKey,
     #[error("index error: {0}")]
     Index(String),
     #[error("video not found: {0}")]
     VideoNotFound(String),
+    #[error("{video_id} has no segment {index}")]
+    SegmentNotFound { video_id: String, index: usize },
+    #[error("`{prefix}` matches several videos: {}", .candidates.join(", "))]
+    AmbiguousVideoId {
+        prefix: String,
+        candidates: Vec<String>,
+    },
+    #[error("invalid index config: {0}")]
+    InvalidConfig(String),
+    #[error(
+        "store was embedded with `{stored}` but the active model is `{active}`; \
+         distances between the two are meaningless"
+    )]
+    ModelMismatch { stored: String, active: String },
+    #[error(
+        "`{model}` returns {actual}-dimensional embeddings, but the index holds \
+         {expected}-dimensional vectors; pick a model with {expected} dimensions"
+    )]
+    DimensionMismatch {
+        model: String,
+        expected: usize,
+        actual: usize,
+    },
+    #[error(
+        "index {0} could not be loaded ({1}); run `sawtrs verify --force-reset` to back it \
+         up and rebuild it from the stored metadata"
+    )]
+    CorruptIndex(String, String),
+    #[error("store was opened read-only")]
+    ReadOnly,
+    #[error(
+        "store {0} is open for writing in another process; try again once it has finished"
+    )]
+    Locked(String),
+    #[error("{0} already holds a transcript, pick another namespace for documents")]
+    NamespaceInUse(String),
+    #[error("{0} is not a sawtrs metadata file: {1}")]
+    InvalidMetadataFile(String, serde_json::Error),
+    #[error("binary metadata file {0} is unreadable: {1}")]
+    CorruptMetadata(String, String),
+    #[error("interrupted, no changes written")]
+    Interrupted,
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error("json error: {0}")]
@@ -39,9 +105,12 @@ pub struct TranscriptSegment {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    pub confidence: Option<f32>,
+    /// The transcriber's original text, if `text` was cleaned up from it.
+    pub raw_text: Option<String>,
 }
 
-/// Segment stored in metadata.json.
+/// Segment stored in metadata.json (or metadata.bin).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredSegment {
     pub video_id: String,
@@ -50,16 +119,282 @@ pub struct StoredSegment {
     pub end: f64,
     pub text: String,
     pub key: u64,
+    /// Mean token probability from whisper; absent in stores written before it was tracked.
+    #[serde(default)]
+    pub confidence: Option<f32>,
+    /// Set for entries stored with `store_documents`, whose `start`/`end` are 0 and
+    /// whose `video_id` is the document namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<DocumentInfo>,
+    /// Untouched transcriber output when it differs from `text`, for auditing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_text: Option<String>,
+}
+
+/// What a store call did: how many entries were embedded and stored, and the indices
+/// of those whose embedding batch failed. Failed entries keep their metadata without a
+/// vector until `retry_failed` embeds them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoreOutcome {
+    pub stored: usize,
+    pub failed: Vec<usize>,
+}
+
+/// A free-text document to index next to transcripts, e.g. a note. It has no timing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+    /// Arbitrary caller data, stored and returned as-is.
+    #[serde(default)]
+    pub meta: serde_json::Value,
+}
+
+/// The document-specific part of a stored entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub meta: serde_json::Value,
+}
+
+/// A document returned by `search_documents`.
+#[derive(Debug, Clone)]
+pub struct DocumentHit {
+    pub namespace: String,
+    pub id: String,
+    pub text: String,
+    pub meta: serde_json::Value,
+    pub distance: f32,
+}
+
+/// Per-video information stored in videos.json. Every field is optional so the
+/// store works for videos ingested without a metadata lookup.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VideoInfo {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Duration in seconds.
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Upload date as `YYYY-MM-DD`.
+    #[serde(default)]
+    pub upload_date: Option<String>,
+    /// Language the video was transcribed in (given or auto-detected), e.g. `en`.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// The YouTube video ID, when the transcript is stored under another name.
+    #[serde(default)]
+    pub source_id: Option<String>,
+    /// Labels for organizing videos, e.g. `podcast`, `lecture`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The WAV the transcript was made from, as the pipeline saw it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wav_path: Option<PathBuf>,
+}
+
+/// Restricts which stored segments a search may return. The default matches everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchFilter<'a> {
+    /// Only segments of this video.
+    pub video_id: Option<&'a str>,
+    /// Only videos transcribed in this language (compared case-insensitively).
+    pub language: Option<&'a str>,
+    /// `Some(true)` for documents only, `Some(false)` for transcript segments only.
+    pub documents: Option<bool>,
+    /// Only videos carrying this tag (compared case-insensitively).
+    pub tag: Option<&'a str>,
+}
+
+impl SearchFilter<'_> {
+    fn is_empty(&self) -> bool {
+        self.video_id.is_none()
+            && self.language.is_none()
+            && self.documents.is_none()
+            && self.tag.is_none()
+    }
 }
 
 /// Result returned by search.
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub video_id: String,
+    pub index: usize,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub distance: f32,
+    pub confidence: Option<f32>,
+}
+
+/// A search hit joined with its neighbouring segments into one stretch of text,
+/// e.g. to hand an LLM as context.
+#[derive(Debug, Clone)]
+pub struct Passage {
+    pub video_id: String,
+    /// Start of the first segment in the passage.
     pub start: f64,
+    /// End of the last segment in the passage.
     pub end: f64,
+    /// Segment texts joined with spaces.
     pub text: String,
+    /// Distance of the best hit the passage contains.
+    pub distance: f32,
+}
+
+/// Raw nearest-neighbour hit from the index, before it is joined with metadata.
+#[derive(Debug, Clone)]
+pub struct KeyMatch {
+    pub key: u64,
     pub distance: f32,
+    /// `None` when the key is in the index but has no metadata entry (desync).
+    pub segment: Option<StoredSegment>,
+}
+
+impl KeyMatch {
+    /// Convert into a search result; misses yield `None`.
+    pub fn into_result(self) -> Option<SearchResult> {
+        let seg = self.segment?;
+        Some(SearchResult {
+            video_id: seg.video_id,
+            index: seg.index,
+            start: seg.start,
+            end: seg.end,
+            text: seg.text,
+            distance: self.distance,
+            confidence: seg.confidence,
+        })
+    }
+}
+
+/// Keys present in only one of the two persisted files.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Vectors in the index with no metadata entry.
+    pub index_only: Vec<u64>,
+    /// Metadata entries with no vector in the index: segments whose embedding failed
+    /// (see `store_transcript`) or whose vector was lost. Not dangling: `retry_failed`
+    /// embeds them again from the stored text, so `repair` keeps them.
+    pub unembedded: Vec<u64>,
+}
+
+impl VerifyReport {
+    /// Whether every vector has metadata and every segment a vector.
+    pub fn is_consistent(&self) -> bool {
+        self.index_only.is_empty() && self.unembedded.is_empty()
+    }
+}
+
+/// HNSW parameters, persisted next to the index when the store is created.
+///
+/// `connectivity` and `expansion_add` shape the graph and are fixed for the life of
+/// the index, as is `multi`; `expansion_search` only affects queries and may be
+/// overridden per open. The embedding model and its dimension are recorded on the first write.
+/// `task_prefixes` is on for new stores; older ones get it from `reembed_with_prefixes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexConfig {
+    pub connectivity: usize,
+    pub expansion_add: usize,
+    pub expansion_search: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_dim: Option<usize>,
+    /// Embed stored texts as `search_document: ...` and queries as `search_query: ...`.
+    #[serde(default)]
+    pub task_prefixes: bool,
+    /// Let usearch hold several vectors per key, for library users who add their own
+    /// vectors to the index. Storing segments still keeps exactly one vector per key.
+    #[serde(default)]
+    pub multi: bool,
+    /// How segment metadata is written; see `MetadataFormat`.
+    #[serde(default)]
+    pub metadata_format: MetadataFormat,
+}
+
+/// On-disk format of the segment metadata. Either file is read regardless of this
+/// setting; it decides what the next write produces.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataFormat {
+    /// Pretty-printed `metadata.json`, readable and diffable.
+    #[default]
+    Json,
+    /// Compact `metadata.bin`, faster to load and save for large stores.
+    Binary,
+}
+
+impl MetadataFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            MetadataFormat::Json => METADATA_FILE,
+            MetadataFormat::Binary => METADATA_BINARY_FILE,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            MetadataFormat::Json => MetadataFormat::Binary,
+            MetadataFormat::Binary => MetadataFormat::Json,
+        }
+    }
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            connectivity: 16,
+            expansion_add: 128,
+            expansion_search: 64,
+            embedding_model: None,
+            embedding_dim: None,
+            task_prefixes: false,
+            multi: false,
+            metadata_format: MetadataFormat::Json,
+        }
+    }
+}
+
+impl IndexConfig {
+    fn validate(&self) -> Result<(), StoreError> {
+        if !(2..=256).contains(&self.connectivity) {
+            return Err(StoreError::InvalidConfig(format!(
+                "connectivity must be between 2 and 256, got {}",
+                self.connectivity
+            )));
+        }
+        if !(1..=4096).contains(&self.expansion_add) {
+            return Err(StoreError::InvalidConfig(format!(
+                "expansion_add must be between 1 and 4096, got {}",
+                self.expansion_add
+            )));
+        }
+        if !(1..=4096).contains(&self.expansion_search) {
+            return Err(StoreError::InvalidConfig(format!(
+                "expansion_search must be between 1 and 4096, got {}",
+                self.expansion_search
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Optional overrides for `IndexConfig` when opening a store.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexTuning {
+    pub connectivity: Option<usize>,
+    pub expansion_add: Option<usize>,
+    pub expansion_search: Option<usize>,
+    /// `IndexConfig::multi` for a new store.
+    pub multi: Option<bool>,
+    /// Switch `IndexConfig::metadata_format`; the change is recorded and the metadata
+    /// is converted on the next write.
+    pub metadata_format: Option<MetadataFormat>,
 }
 
 // ── Deterministic ID: FNV-1a ────────────────────────────────────────────
@@ -73,12 +408,25 @@ fn fnv1a_hash(s: &str) -> u64 {
     hash
 }
 
+/// Candidate keys for segment `index` of `video_id`: the plain hash of
+/// `{video_id}_{index}`, then salted variants (`{video_id}_{index}#1`, ...) used
+/// when an earlier candidate already belongs to a different segment.
+fn candidate_keys(video_id: &str, index: usize) -> impl Iterator<Item = u64> {
+    let base = format!("{video_id}_{index}");
+    (0u32..).map(move |salt| match salt {
+        0 => fnv1a_hash(&base),
+        _ => fnv1a_hash(&format!("{base}#{salt}")),
+    })
+}
+
 // ── Ollama embeddings ───────────────────────────────────────────────────
 
 #[derive(Serialize)]
 struct EmbedRequest<'a> {
     model: &'a str,
     input: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -86,81 +434,474 @@ struct EmbedResponse {
     embeddings: Vec<Vec<f32>>,
 }
 
-fn embed_texts(texts: &[&str]) -> Result<Vec<Vec<f32>>, StoreError> {
-    let client = reqwest::blocking::Client::new();
-    let body = EmbedRequest {
-        model: EMBEDDING_MODEL,
-        input: texts.to_vec(),
+/// Ollama embedding client. Keeps one HTTP client (and its connection pool) for
+/// every request and remembers the most recent query embeddings, so repeating a
+/// search skips the round-trip.
+pub struct Embedder {
+    client: reqwest::blocking::Client,
+    query_cache: Mutex<QueryCache>,
+    keep_alive: Option<serde_json::Value>,
+    model: String,
+    /// Sent as the `Authorization` header; never printed.
+    api_key: Option<String>,
+}
+
+/// Small LRU of query text → embedding; the back of `order` is the most recent.
+#[derive(Default)]
+struct QueryCache {
+    entries: HashMap<String, Vec<f32>>,
+    order: VecDeque<String>,
+}
+
+impl Default for Embedder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Embedder {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::blocking::Client::new(),
+            query_cache: Mutex::new(QueryCache::default()),
+            keep_alive: None,
+            model: EMBEDDING_MODEL.to_string(),
+            api_key: api_key_from_env(),
+        }
+    }
+
+    /// The Ollama model texts are embedded with.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embed with `model` instead of `nomic-embed-text`. Clears the query cache.
+    pub fn set_model(&mut self, model: &str) {
+        self.model = model.to_string();
+        *self.cache() = QueryCache::default();
+    }
+
+    /// How long Ollama keeps the embedding model loaded after each request: a duration
+    /// such as `10m` or `1h` (negative keeps it loaded), or plain seconds. Without it
+    /// Ollama's default applies and an idle model is unloaded, slowing the next call.
+    pub fn set_keep_alive(&mut self, keep_alive: &str) {
+        self.keep_alive = Some(keep_alive_value(keep_alive));
+    }
+
+    /// Authenticate every request with `key` instead of `$OLLAMA_API_KEY`: a bearer
+    /// token, or a full header value such as `Basic dXNlcjpwYXNz`.
+    pub fn set_api_key(&mut self, key: &str) {
+        self.api_key = Some(key.to_string());
+    }
+
+    /// Embed `texts` in a single request.
+    pub fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, StoreError> {
+        let body = EmbedRequest {
+            model: &self.model,
+            input: texts.to_vec(),
+            keep_alive: self.keep_alive.as_ref(),
+        };
+
+        let mut request = self.client.post(OLLAMA_EMBED_URL).json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization(key)?);
+        }
+        let resp = request
+            .send()
+            .map_err(|e| {
+                if e.is_connect() {
+                    StoreError::OllamaUnavailable
+                } else {
+                    StoreError::Http(e)
+                }
+            })?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().unwrap_or_default();
+            let hint = match status {
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                    format!(" (check {OLLAMA_API_KEY_ENV_VAR})")
+                }
+                _ => String::new(),
+            };
+            return Err(StoreError::EmbeddingFailed(format!(
+                "HTTP {status}: {body}{hint}"
+            )));
+        }
+
+        let parsed: EmbedResponse = resp.json()?;
+        if parsed.embeddings.len() != texts.len() {
+            return Err(StoreError::EmbeddingFailed(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                parsed.embeddings.len()
+            )));
+        }
+
+        Ok(parsed.embeddings)
+    }
+
+    /// Embed a search query, answering from the cache when it was embedded recently.
+    pub fn embed_query(&self, query: &str) -> Result<Vec<f32>, StoreError> {
+        if let Some(hit) = self.cache().get(query) {
+            return Ok(hit);
+        }
+
+        let embedding = self.embed(&[query])?.swap_remove(0);
+        self.cache().insert(query, embedding.clone());
+        Ok(embedding)
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, QueryCache> {
+        // A poisoned cache only means a panic mid-insert; the data is still usable
+        self.query_cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The credentials in `$OLLAMA_API_KEY`, unless it is unset or blank.
+pub(crate) fn api_key_from_env() -> Option<String> {
+    std::env::var(OLLAMA_API_KEY_ENV_VAR).ok().filter(|k| !k.trim().is_empty())
+}
+
+/// Ollama's `keep_alive` request field: plain seconds as a number, anything else
+/// (`10m`, `-1h`) as the duration string Ollama parses itself.
+pub(crate) fn keep_alive_value(keep_alive: &str) -> serde_json::Value {
+    let keep_alive = keep_alive.trim();
+    match keep_alive.parse::<i64>() {
+        Ok(seconds) => seconds.into(),
+        Err(_) => keep_alive.into(),
+    }
+}
+
+/// `Authorization` header for `key`: used as-is when it already names a scheme
+/// (`Bearer ...`, `Basic ...`), otherwise sent as a bearer token. Marked sensitive so
+/// reqwest leaves it out of debug output.
+pub(crate) fn authorization(key: &str) -> Result<reqwest::header::HeaderValue, StoreError> {
+    let key = key.trim();
+    let value = match key.split_once(' ') {
+        Some((scheme, _))
+            if scheme.eq_ignore_ascii_case("bearer") || scheme.eq_ignore_ascii_case("basic") =>
+        {
+            key.to_string()
+        }
+        _ => format!("Bearer {key}"),
     };
+    let mut header =
+        reqwest::header::HeaderValue::from_str(&value).map_err(|_| StoreError::InvalidApiKey)?;
+    header.set_sensitive(true);
+    Ok(header)
+}
 
-    let resp = client
-        .post(OLLAMA_EMBED_URL)
-        .json(&body)
-        .send()
-        .map_err(|e| {
-            if e.is_connect() {
-                StoreError::OllamaUnavailable
-            } else {
-                StoreError::Http(e)
-            }
-        })?;
+impl QueryCache {
+    fn get(&mut self, query: &str) -> Option<Vec<f32>> {
+        let embedding = self.entries.get(query)?.clone();
+        self.touch(query);
+        Some(embedding)
+    }
+
+    fn insert(&mut self, query: &str, embedding: Vec<f32>) {
+        if self.entries.insert(query.to_string(), embedding).is_some() {
+            self.touch(query);
+            return;
+        }
+        self.order.push_back(query.to_string());
+        if self.order.len() > QUERY_CACHE_SIZE
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn touch(&mut self, query: &str) {
+        if let Some(pos) = self.order.iter().position(|q| q == query) {
+            let q = self.order.remove(pos).unwrap_or_default();
+            self.order.push_back(q);
+        }
+    }
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().unwrap_or_default();
-        return Err(StoreError::EmbeddingFailed(format!(
-            "HTTP {status}: {body}"
-        )));
+/// Version of the usearch crate the index is built with.
+pub fn index_version() -> &'static str {
+    usearch::version()
+}
+
+// ── Metadata without the index ──────────────────────────────────────────
+
+/// Read one video's segments, sorted by start time, straight from a store's
+/// `metadata.json` or `metadata.bin`, without opening the vector index. Useful for
+/// exporting from a copy of the metadata alone.
+pub fn read_segments_file(path: &Path, video_id: &str) -> Result<Vec<StoredSegment>, StoreError> {
+    let metadata = read_metadata_file(path).map_err(|e| match e {
+        StoreError::Json(e) => StoreError::InvalidMetadataFile(path.display().to_string(), e),
+        e => e,
+    })?;
+    video_segments(&metadata, video_id)
+}
+
+/// Load a metadata file in either format, told apart by the binary header.
+fn read_metadata_file(path: &Path) -> Result<HashMap<u64, StoredSegment>, StoreError> {
+    let data = fs::read(path)?;
+    if data.starts_with(BINARY_METADATA_MAGIC) {
+        decode_metadata(&data)
+            .map_err(|e| StoreError::CorruptMetadata(path.display().to_string(), e))
+    } else {
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+// ── Binary metadata ─────────────────────────────────────────────────────
+//
+// Layout: magic, little-endian u32 version, then the segments sorted by key as a
+// MessagePack array of `StoredSegment` maps (field names kept, so the serde defaults
+// for absent fields apply as they do for metadata.json).
+
+fn encode_metadata(metadata: &HashMap<u64, StoredSegment>) -> Result<Vec<u8>, StoreError> {
+    let mut segments: Vec<&StoredSegment> = metadata.values().collect();
+    segments.sort_unstable_by_key(|seg| seg.key);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(BINARY_METADATA_MAGIC);
+    out.extend_from_slice(&BINARY_METADATA_VERSION.to_le_bytes());
+    rmp_serde::encode::write_named(&mut out, &segments)
+        .map_err(|e| StoreError::Io(std::io::Error::other(e)))?;
+    Ok(out)
+}
+
+fn decode_metadata(data: &[u8]) -> Result<HashMap<u64, StoredSegment>, String> {
+    let header = BINARY_METADATA_MAGIC.len();
+    let version = data
+        .get(header..header + 4)
+        .map(|v| u32::from_le_bytes(v.try_into().expect("slice of 4 bytes")))
+        .ok_or("truncated header")?;
+    if version != BINARY_METADATA_VERSION {
+        return Err(format!("unsupported format version {version}"));
     }
 
-    let parsed: EmbedResponse = resp.json()?;
-    if parsed.embeddings.len() != texts.len() {
-        return Err(StoreError::EmbeddingFailed(format!(
-            "expected {} embeddings, got {}",
-            texts.len(),
-            parsed.embeddings.len()
-        )));
+    let mut body = &data[header + 4..];
+    let segments: Vec<StoredSegment> =
+        rmp_serde::from_read(&mut body).map_err(|e| e.to_string())?;
+    if !body.is_empty() {
+        return Err(format!("{} trailing bytes", body.len()));
+    }
+    Ok(segments.into_iter().map(|seg| (seg.key, seg)).collect())
+}
+
+fn video_segments(
+    metadata: &HashMap<u64, StoredSegment>,
+    video_id: &str,
+) -> Result<Vec<StoredSegment>, StoreError> {
+    let mut segments: Vec<StoredSegment> = metadata
+        .values()
+        .filter(|seg| seg.video_id == video_id)
+        .cloned()
+        .collect();
+
+    if segments.is_empty() {
+        return Err(StoreError::VideoNotFound(video_id.to_string()));
     }
 
-    Ok(parsed.embeddings)
+    segments.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(segments)
 }
 
 // ── VectorStore ─────────────────────────────────────────────────────────
 
+fn index_options(config: &IndexConfig) -> IndexOptions {
+    IndexOptions {
+        dimensions: EMBEDDING_DIM,
+        metric: MetricKind::Cos,
+        quantization: ScalarKind::F32,
+        connectivity: config.connectivity,
+        expansion_add: config.expansion_add,
+        expansion_search: config.expansion_search,
+        multi: config.multi,
+    }
+}
+
+/// How `open_mode` opens a store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    ReadWrite,
+    /// Changeable like `ReadWrite`, but only in memory; nothing is written.
+    Unpersisted,
+    /// Loaded into memory; nothing is written.
+    ReadOnly,
+    /// Index memory-mapped instead of loaded; nothing is written or changed.
+    View,
+}
+
 pub struct VectorStore {
     data_dir: PathBuf,
     index: Index,
     metadata: HashMap<u64, StoredSegment>,
+    videos: HashMap<String, VideoInfo>,
+    config: IndexConfig,
+    embedder: Embedder,
+    cancel: Option<Arc<AtomicBool>>,
+    read_only: bool,
+    /// Cleared by `set_persist(false)`: changes stay in memory.
+    persist_changes: bool,
+    /// Held (locked) for as long as a writable store is open; `None` otherwise.
+    _lock: Option<fs::File>,
 }
 
 impl VectorStore {
     /// Create or load a vector store from `data_dir`.
     pub fn open(data_dir: &str) -> Result<Self, StoreError> {
+        Self::open_with(data_dir, IndexTuning::default())
+    }
+
+    /// Create or load a vector store, applying HNSW overrides.
+    ///
+    /// For a new store the resulting config is persisted. For an existing index,
+    /// overriding `connectivity` or `expansion_add` with a different value is an
+    /// error; `expansion_search` applies to this session only.
+    ///
+    /// Only one process can have a store open for writing: until this `VectorStore`
+    /// is dropped, opening it writable elsewhere fails with `StoreError::Locked`.
+    /// Read-only and unpersisted stores don't take the lock.
+    pub fn open_with(data_dir: &str, tuning: IndexTuning) -> Result<Self, StoreError> {
+        Self::open_mode(data_dir, tuning, Access::ReadWrite)
+    }
+
+    /// Load a store for reading only, e.g. to search while another process ingests.
+    ///
+    /// Nothing is created or written: a missing store opens empty, and anything that
+    /// would persist fails with `StoreError::ReadOnly`. Writers replace each file
+    /// atomically, so this sees the state as of the last completed write, possibly
+    /// with the index a write ahead of the metadata (such hits are dropped).
+    pub fn open_read_only(data_dir: &str, tuning: IndexTuning) -> Result<Self, StoreError> {
+        Self::open_mode(data_dir, tuning, Access::ReadOnly)
+    }
+
+    /// Like `open_read_only`, but memory-maps the index file (usearch's `view`) instead
+    /// of reading it into RAM, so a huge index opens at once and only the pages a
+    /// search touches are read. A viewed index can't be modified at all: besides
+    /// persisting, anything that would change it fails with `StoreError::ReadOnly`.
+    /// A writer replacing the file doesn't disturb the mapping, which keeps showing
+    /// the index as it was when opened.
+    pub fn open_view(data_dir: &str, tuning: IndexTuning) -> Result<Self, StoreError> {
+        Self::open_mode(data_dir, tuning, Access::View)
+    }
+
+    /// Like `open_with` followed by `set_persist(false)`, except that opening writes
+    /// nothing either: a new store gets no directory or config file, and a metadata
+    /// format switch in `tuning` isn't recorded. For `--no-persist` runs.
+    pub fn open_unpersisted(data_dir: &str, tuning: IndexTuning) -> Result<Self, StoreError> {
+        Self::open_mode(data_dir, tuning, Access::Unpersisted)
+    }
+
+    fn open_mode(data_dir: &str, tuning: IndexTuning, access: Access) -> Result<Self, StoreError> {
+        let read_only = matches!(access, Access::ReadOnly | Access::View);
+        let writes = access == Access::ReadWrite;
         let data_dir = PathBuf::from(data_dir);
-        fs::create_dir_all(&data_dir)?;
+        // Taken before anything is read, so this process starts from the last writer's
+        // state and no other process can save over its changes
+        let lock = if writes {
+            fs::create_dir_all(&data_dir)?;
+            Some(lock_store(&data_dir)?)
+        } else {
+            None
+        };
 
-        let options = IndexOptions {
-            dimensions: EMBEDDING_DIM,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
+        let index_path = data_dir.join(INDEX_FILE);
+        let config_path = data_dir.join(INDEX_CONFIG_FILE);
+
+        let persisted: Option<IndexConfig> = if config_path.exists() {
+            let data = fs::read_to_string(&config_path)?;
+            Some(serde_json::from_str(&data)?)
+        } else {
+            None
         };
+        let base = persisted.clone().unwrap_or_default();
 
-        let index = Index::new(&options).map_err(|e| StoreError::Index(e.to_string()))?;
+        if index_path.exists() {
+            if let Some(c) = tuning.connectivity.filter(|&c| c != base.connectivity) {
+                return Err(StoreError::InvalidConfig(format!(
+                    "connectivity is fixed once the index exists (store uses {}, requested {c})",
+                    base.connectivity
+                )));
+            }
+            if let Some(e) = tuning.expansion_add.filter(|&e| e != base.expansion_add) {
+                return Err(StoreError::InvalidConfig(format!(
+                    "expansion_add is fixed once the index exists (store uses {}, requested {e})",
+                    base.expansion_add
+                )));
+            }
+            if let Some(m) = tuning.multi.filter(|&m| m != base.multi) {
+                return Err(StoreError::InvalidConfig(format!(
+                    "multi is fixed once the index exists (store uses {}, requested {m})",
+                    base.multi
+                )));
+            }
+        }
+
+        let mut config = IndexConfig {
+            connectivity: tuning.connectivity.unwrap_or(base.connectivity),
+            expansion_add: tuning.expansion_add.unwrap_or(base.expansion_add),
+            expansion_search: tuning.expansion_search.unwrap_or(base.expansion_search),
+            embedding_model: base.embedding_model.clone(),
+            embedding_dim: base.embedding_dim,
+            // New stores embed with task prefixes; existing ones keep what they have
+            task_prefixes: base.task_prefixes || !index_path.exists(),
+            multi: tuning.multi.unwrap_or(base.multi),
+            metadata_format: tuning.metadata_format.unwrap_or(base.metadata_format),
+        };
+        config.validate()?;
+
+        if persisted.is_none() && writes {
+            // Stores created before the config file existed used the defaults
+            let to_persist = if index_path.exists() {
+                &IndexConfig { metadata_format: config.metadata_format, ..base.clone() }
+            } else {
+                &config
+            };
+            write_atomic(&config_path, serde_json::to_string_pretty(to_persist)?)?;
+        } else if writes
+            && let Some(persisted) =
+                persisted.filter(|p| p.metadata_format != config.metadata_format)
+        {
+            let switched = IndexConfig { metadata_format: config.metadata_format, ..persisted };
+            write_atomic(&config_path, serde_json::to_string_pretty(&switched)?)?;
+        }
+        if config.embedding_model.is_none() && index_path.exists() {
+            // Indexes written before the model was recorded always used the built-in one
+            config.embedding_model = Some(EMBEDDING_MODEL.to_string());
+            config.embedding_dim = Some(EMBEDDING_DIM);
+        }
+
+        let index = Index::new(&index_options(&config))
+            .map_err(|e| StoreError::Index(e.to_string()))?;
 
-        let index_path = data_dir.join(INDEX_FILE);
         if index_path.exists() {
-            index
-                .load(index_path.to_str().unwrap_or_default())
-                .map_err(|e| StoreError::Index(e.to_string()))?;
+            let path = index_path.to_str().unwrap_or_default();
+            let opened = match access {
+                Access::View => index.view(path),
+                Access::ReadWrite | Access::Unpersisted | Access::ReadOnly => index.load(path),
+            };
+            opened.map_err(|e| {
+                StoreError::CorruptIndex(index_path.display().to_string(), e.to_string())
+            })?;
+            // The loaded file carries its own search expansion; reapply ours
+            index.change_expansion_search(config.expansion_search);
         }
 
-        let metadata_path = data_dir.join(METADATA_FILE);
-        let metadata: HashMap<u64, StoredSegment> = if metadata_path.exists() {
-            let data = fs::read_to_string(&metadata_path)?;
+        // Whichever file is there; both only after a crash mid-switch, when the
+        // configured format is the one written last
+        let format = config.metadata_format;
+        let metadata_path = [format, format.other()]
+            .map(|f| data_dir.join(f.file_name()))
+            .into_iter()
+            .find(|path| path.exists());
+        let metadata: HashMap<u64, StoredSegment> = match metadata_path {
+            Some(path) => read_metadata_file(&path)?,
+            None => HashMap::new(),
+        };
+
+        let videos_path = data_dir.join(VIDEOS_FILE);
+        let videos: HashMap<String, VideoInfo> = if videos_path.exists() {
+            let data = fs::read_to_string(&videos_path)?;
             serde_json::from_str(&data)?
         } else {
             HashMap::new()
@@ -170,130 +911,440 @@ impl VectorStore {
             data_dir,
             index,
             metadata,
+            videos,
+            config,
+            embedder: Embedder::new(),
+            cancel: None,
+            read_only,
+            persist_changes: access != Access::Unpersisted,
+            _lock: lock,
         })
     }
 
-    /// Embed and store transcript segments for a video.
-    pub fn store_transcript(
-        &mut self,
-        video_id: &str,
-        segments: &[TranscriptSegment],
-    ) -> Result<usize, StoreError> {
-        if segments.is_empty() {
-            return Ok(0);
-        }
+    /// Abort long operations with `StoreError::Interrupted` once `flag` is set
+    /// (e.g. from a Ctrl-C handler). Checked between embedding batches and before
+    /// anything is written, so an interrupted store is left untouched on disk.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel = Some(flag);
+    }
 
-        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
-        let embeddings = embed_texts(&texts)?;
+    /// Ask Ollama to keep the embedding model loaded between requests; see
+    /// `Embedder::set_keep_alive`.
+    pub fn set_keep_alive(&mut self, keep_alive: &str) {
+        self.embedder.set_keep_alive(keep_alive);
+    }
 
-        // Reserve capacity for new entries
-        let new_capacity = self.index.size() + segments.len();
-        self.index
-            .reserve(new_capacity)
-            .map_err(|e| StoreError::Index(e.to_string()))?;
+    /// Authenticate Ollama requests with `key`; see `Embedder::set_api_key`.
+    pub fn set_api_key(&mut self, key: &str) {
+        self.embedder.set_api_key(key);
+    }
 
-        for (i, (seg, embedding)) in segments.iter().zip(embeddings.iter()).enumerate() {
-            let key_str = format!("{video_id}_{i}");
-            let key = fnv1a_hash(&key_str);
+    /// With `false`, storing, deleting and the other changes still apply in memory, so
+    /// later searches on this `VectorStore` see them, but nothing is written to disk
+    /// and they are lost when it is dropped. For experiments and tests. Opening a store
+    /// that doesn't exist yet has already created its directory and config file;
+    /// `open_unpersisted` doesn't.
+    pub fn set_persist(&mut self, persist: bool) {
+        self.persist_changes = persist;
+    }
 
-            // Remove old entry if it exists (idempotent upsert)
-            if self.index.contains(key) {
-                let _ = self.index.remove(key);
-            }
+    /// Embed with `model` instead of `nomic-embed-text`; see `check_embedding_model`.
+    pub fn set_embedding_model(&mut self, model: &str) {
+        self.embedder.set_model(model);
+    }
 
-            self.index
-                .add(key, embedding)
-                .map_err(|e| StoreError::Index(e.to_string()))?;
+    /// The model the stored vectors were embedded with, if anything was stored yet.
+    pub fn embedding_model(&self) -> Option<&str> {
+        self.config.embedding_model.as_deref()
+    }
 
-            self.metadata.insert(
-                key,
-                StoredSegment {
-                    video_id: video_id.to_string(),
-                    index: i,
-                    start: seg.start,
-                    end: seg.end,
-                    text: seg.text.clone(),
-                    key,
-                },
-            );
-        }
+    /// Length of the stored vectors, if anything was stored yet.
+    pub fn embedding_dim(&self) -> Option<usize> {
+        self.config.embedding_dim
+    }
 
-        self.persist()?;
-        Ok(segments.len())
+    /// Whether texts and queries are embedded with nomic task prefixes; see
+    /// `IndexConfig::task_prefixes`.
+    pub fn task_prefixes(&self) -> bool {
+        self.config.task_prefixes
     }
 
-    /// Semantic search across stored segments.
-    pub fn search(
-        &self,
-        query: &str,
-        n: usize,
-        video_id_filter: Option<&str>,
-    ) -> Result<Vec<SearchResult>, StoreError> {
-        if self.index.size() == 0 {
-            return Ok(Vec::new());
+    /// Fail with `StoreError::ModelMismatch` if the store was embedded with a different
+    /// model than the active one. Storing always checks; searching leaves it to the
+    /// caller, since a mismatched search still runs but ranks by meaningless distances.
+    pub fn check_embedding_model(&self) -> Result<(), StoreError> {
+        match self.embedding_model() {
+            Some(stored) if stored != self.embedder.model() => Err(StoreError::ModelMismatch {
+                stored: stored.to_string(),
+                active: self.embedder.model().to_string(),
+            }),
+            _ => Ok(()),
         }
+    }
+
+    /// Fail with `StoreError::DimensionMismatch` unless `embedding` fits the index,
+    /// whose vectors are always `EMBEDDING_DIM` long.
+    fn check_dimension(&self, embedding: &[f32]) -> Result<(), StoreError> {
+        if embedding.len() == EMBEDDING_DIM {
+            return Ok(());
+        }
+        Err(StoreError::DimensionMismatch {
+            model: self.embedder.model().to_string(),
+            expected: EMBEDDING_DIM,
+            actual: embedding.len(),
+        })
+    }
 
-        let embeddings = embed_texts(&[query])?;
-        let query_vec = &embeddings[0];
+    fn check_cancelled(&self) -> Result<(), StoreError> {
+        match &self.cancel {
+            Some(flag) if flag.load(Ordering::SeqCst) => Err(StoreError::Interrupted),
+            _ => Ok(()),
+        }
+    }
 
-        let matches = match video_id_filter {
-            Some(vid) => {
-                let vid = vid.to_string();
-                self.index
-                    .filtered_search(query_vec, n, |key| {
-                        self.metadata
-                            .get(&key)
-                            .is_some_and(|seg| seg.video_id == vid)
-                    })
-                    .map_err(|e| StoreError::Index(e.to_string()))?
+    /// Embed `prefix` + each text in batches, reporting `(done, total)` after each.
+    fn embed_batched_with(
+        &self,
+        texts: &[&str],
+        prefix: &str,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Vec<Vec<f32>>, StoreError> {
+        let texts: Vec<String> = texts.iter().map(|t| format!("{prefix}{t}")).collect();
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(EMBED_BATCH_SIZE) {
+            self.check_cancelled()?;
+            let batch: Vec<&str> = batch.iter().map(String::as_str).collect();
+            embeddings.extend(self.embedder.embed(&batch)?);
+            if let Some(first) = embeddings.first() {
+                self.check_dimension(first)?;
             }
-            None => self
-                .index
-                .search(query_vec, n)
-                .map_err(|e| StoreError::Index(e.to_string()))?,
-        };
-
-        let results = matches
-            .keys
-            .iter()
-            .zip(matches.distances.iter())
-            .filter_map(|(&key, &distance)| {
-                let seg = self.metadata.get(&key)?;
-                Some(SearchResult {
-                    video_id: seg.video_id.clone(),
-                    start: seg.start,
-                    end: seg.end,
-                    text: seg.text.clone(),
-                    distance,
-                })
-            })
-            .collect();
+            progress(embeddings.len(), texts.len());
+        }
+        self.check_cancelled()?;
+        Ok(embeddings)
+    }
 
-        Ok(results)
+    /// Re-embed every stored text with the `search_document:` task prefix and rebuild
+    /// the index from scratch, for stores written before prefixes were used. The
+    /// stored texts are reused, so nothing is downloaded or transcribed again.
+    ///
+    /// Returns how many entries were re-embedded: 0 if the store already uses
+    /// prefixes, so running it twice is harmless. `progress` gets `(done, total)`
+    /// after each batch. Nothing is written if embedding fails or is cancelled.
+    /// Index keys without metadata are dropped by the rebuild.
+    pub fn reembed_with_prefixes(
+        &mut self,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, StoreError> {
+        if self.config.task_prefixes {
+            return Ok(0);
+        }
+        self.rebuild_with(DOCUMENT_PREFIX, &mut progress)
     }
 
-    /// Get all segments for a video, sorted by start time.
-    pub fn get_segments(&self, video_id: &str) -> Result<Vec<StoredSegment>, StoreError> {
-        let mut segments: Vec<StoredSegment> = self
-            .metadata
-            .values()
-            .filter(|seg| seg.video_id == video_id)
-            .cloned()
-            .collect();
+    /// Re-embed every stored text and replace the index with the result, e.g. after
+    /// `backup_index` moved a corrupt one aside. Uses the store's task-prefix setting.
+    /// Progress, cancellation and persistence work as in `reembed_with_prefixes`.
+    pub fn rebuild_index(
+        &mut self,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<usize, StoreError> {
+        let prefix = if self.config.task_prefixes { DOCUMENT_PREFIX } else { "" };
+        self.rebuild_with(prefix, &mut progress)
+    }
 
-        if segments.is_empty() {
-            return Err(StoreError::VideoNotFound(video_id.to_string()));
+    /// Move `data_dir`'s index file aside as `index.usearch.corrupt` (or `.corrupt.1`,
+    /// ...) so the store opens with an empty index. Returns the backup path, or `None`
+    /// if there was no index file. Metadata and the other files are left alone.
+    pub fn backup_index(data_dir: &str) -> Result<Option<PathBuf>, StoreError> {
+        let index_path = Path::new(data_dir).join(INDEX_FILE);
+        if !index_path.exists() {
+            return Ok(None);
         }
-
-        segments.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
-        Ok(segments)
+        let backup = (0u32..)
+            .map(|n| match n {
+                0 => index_path.with_extension("usearch.corrupt"),
+                _ => index_path.with_extension(format!("usearch.corrupt.{n}")),
+            })
+            .find(|path| !path.exists())
+            .expect("backup names are unbounded");
+        fs::rename(&index_path, &backup)?;
+        Ok(Some(backup))
     }
 
-    /// List all stored video IDs.
-    pub fn get_video_ids(&self) -> Vec<String> {
-        let mut ids: Vec<String> = self
+    fn rebuild_with(
+        &mut self,
+        prefix: &str,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<usize, StoreError> {
+        self.check_embedding_model()?;
+
+        let mut keys: Vec<u64> = self.metadata.keys().copied().collect();
+        keys.sort_unstable();
+        let texts: Vec<&str> = keys.iter().map(|k| self.metadata[k].text.as_str()).collect();
+        let embeddings = self.embed_batched_with(&texts, prefix, progress)?;
+
+        let index = Index::new(&index_options(&self.config))
+            .map_err(|e| StoreError::Index(e.to_string()))?;
+        index
+            .reserve(keys.len())
+            .map_err(|e| StoreError::Index(e.to_string()))?;
+        for (key, embedding) in keys.iter().zip(&embeddings) {
+            index
+                .add(*key, embedding)
+                .map_err(|e| StoreError::Index(e.to_string()))?;
+        }
+
+        self.index = index;
+        self.config.task_prefixes = !prefix.is_empty();
+        if self.config.embedding_model.is_none() {
+            self.config.embedding_model = Some(self.embedder.model().to_string());
+            self.config.embedding_dim = embeddings.first().map(Vec::len);
+        }
+        self.persist()?;
+        self.save_recorded_config()?;
+        Ok(keys.len())
+    }
+
+    /// Write the embedding fields of `self.config` to the config file. The rest of the
+    /// file is left alone, since `self.config` may carry a per-session `expansion_search`.
+    fn save_recorded_config(&self) -> Result<(), StoreError> {
+        if self.read_only {
+            return Err(StoreError::ReadOnly);
+        }
+        if !self.persist_changes {
+            return Ok(());
+        }
+        let path = self.data_dir.join(INDEX_CONFIG_FILE);
+        let mut on_disk: IndexConfig = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        on_disk.embedding_model = self.config.embedding_model.clone();
+        on_disk.embedding_dim = self.config.embedding_dim;
+        on_disk.task_prefixes = self.config.task_prefixes;
+        write_atomic(&path, serde_json::to_string_pretty(&on_disk)?)?;
+        Ok(())
+    }
+
+    /// Embed and store transcript segments for a video.
+    ///
+    /// Blank segments and segments with a known confidence below `min_confidence`
+    /// are skipped; the remaining ones are indexed contiguously. Nothing is sent to
+    /// Ollama if none are left.
+    ///
+    /// A failed embedding batch doesn't abort the others: its segments are stored
+    /// without vectors and listed in `StoreOutcome::failed` for `retry_failed`. It is
+    /// an error only if every batch failed.
+    ///
+    /// Segments left over from a longer earlier transcript of the video are removed.
+    pub fn store_transcript(
+        &mut self,
+        video_id: &str,
+        segments: &[TranscriptSegment],
+        min_confidence: Option<f32>,
+    ) -> Result<StoreOutcome, StoreError> {
+        let outcome = self.store_from(video_id, segments, min_confidence, 0)?;
+        self.truncate_video(video_id, outcome.stored + outcome.failed.len())?;
+        Ok(outcome)
+    }
+
+    /// Like `store_transcript`, but continues numbering after the video's highest
+    /// stored index instead of overwriting from 0, so transcripts ingested in parts
+    /// accumulate. Returns `(offset, outcome)` where `offset` is the first index used.
+    pub fn store_transcript_append(
+        &mut self,
+        video_id: &str,
+        segments: &[TranscriptSegment],
+        min_confidence: Option<f32>,
+    ) -> Result<(usize, StoreOutcome), StoreError> {
+        let offset = self.next_index(video_id);
+        let outcome = self.store_from(video_id, segments, min_confidence, offset)?;
+        Ok((offset, outcome))
+    }
+
+    /// Like `store_transcript`, numbering the kept segments from `first_index`, so a
+    /// transcript can be stored in chunks while it is still being produced. Nothing
+    /// is removed; call `truncate_video` once the last chunk is stored.
+    pub fn store_transcript_at(
+        &mut self,
+        video_id: &str,
+        segments: &[TranscriptSegment],
+        min_confidence: Option<f32>,
+        first_index: usize,
+    ) -> Result<StoreOutcome, StoreError> {
+        self.store_from(video_id, segments, min_confidence, first_index)
+    }
+
+    /// One past the video's highest stored index (0 if it has none): where
+    /// `store_transcript_append` continues.
+    pub fn next_index(&self, video_id: &str) -> usize {
+        self.metadata
+            .values()
+            .filter(|seg| seg.video_id == video_id)
+            .map(|seg| seg.index + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Embed and upsert segments with indices starting at `offset`.
+    fn store_from(
+        &mut self,
+        video_id: &str,
+        segments: &[TranscriptSegment],
+        min_confidence: Option<f32>,
+        offset: usize,
+    ) -> Result<StoreOutcome, StoreError> {
+        let entries: Vec<StoredSegment> = segments
+            .iter()
+            .filter(|seg| !seg.text.trim().is_empty())
+            .filter(|seg| match (min_confidence, seg.confidence) {
+                (Some(min), Some(conf)) => conf >= min,
+                _ => true,
+            })
+            .enumerate()
+            .map(|(i, seg)| StoredSegment {
+                video_id: video_id.to_string(),
+                index: offset + i,
+                start: seg.start,
+                end: seg.end,
+                text: seg.text.clone(),
+                key: 0,
+                confidence: seg.confidence,
+                document: None,
+                raw_text: seg.raw_text.clone(),
+            })
+            .collect();
+
+        self.upsert(entries)
+    }
+
+    /// Embed `entries` and upsert them under their deterministic keys (filled in
+    /// here), then persist. Entries whose batch failed to embed keep their metadata
+    /// without a vector; see `store_transcript`.
+    fn upsert(&mut self, entries: Vec<StoredSegment>) -> Result<StoreOutcome, StoreError> {
+        if entries.is_empty() {
+            return Ok(StoreOutcome::default());
+        }
+        // Fail before embedding anything that couldn't be saved (or, for a viewed
+        // index, even added)
+        if self.read_only {
+            return Err(StoreError::ReadOnly);
+        }
+        self.check_embedding_model()?;
+
+        let prefix = if self.config.task_prefixes { DOCUMENT_PREFIX } else { "" };
+        let mut embeddings: Vec<Option<Vec<f32>>> = Vec::with_capacity(entries.len());
+        let mut first_error = None;
+        let mut unavailable = false;
+        for batch in entries.chunks(EMBED_BATCH_SIZE) {
+            self.check_cancelled()?;
+            let result = if unavailable {
+                Err(StoreError::OllamaUnavailable)
+            } else {
+                let texts: Vec<String> =
+                    batch.iter().map(|seg| format!("{prefix}{}", seg.text)).collect();
+                let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+                self.embedder.embed(&texts)
+            };
+            match result {
+                Ok(batch_embeddings) => embeddings.extend(batch_embeddings.into_iter().map(Some)),
+                Err(e) => {
+                    // Without Ollama every later batch would fail the same way
+                    unavailable |= matches!(e, StoreError::OllamaUnavailable);
+                    embeddings.extend(std::iter::repeat_n(None, batch.len()));
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        self.check_cancelled()?;
+        if let Some(e) = first_error
+            && embeddings.iter().all(Option::is_none)
+        {
+            return Err(e);
+        }
+        self.insert_embedded(entries, &embeddings)
+    }
+
+    /// Upsert already-embedded `entries` under their deterministic keys (filled in
+    /// here), then persist. A `None` embedding stores the entry without a vector.
+    fn insert_embedded(
+        &mut self,
+        entries: Vec<StoredSegment>,
+        embeddings: &[Option<Vec<f32>>],
+    ) -> Result<StoreOutcome, StoreError> {
+        if let Some(embedding) = embeddings.iter().flatten().next() {
+            self.check_dimension(embedding)?;
+        }
+        // Reserve capacity for new entries
+        let new_capacity = self.index.size() + entries.len();
+        self.index
+            .reserve(new_capacity)
+            .map_err(|e| StoreError::Index(e.to_string()))?;
+
+        let mut outcome = StoreOutcome::default();
+        for (mut entry, embedding) in entries.into_iter().zip(embeddings.iter()) {
+            let key = self.segment_key(&entry.video_id, entry.index);
+            match embedding {
+                Some(embedding) => {
+                    self.replace_vector(key, embedding)?;
+                    outcome.stored += 1;
+                }
+                None => {
+                    // Don't leave the previous text's vector behind under the new text
+                    let _ = self.index.remove(key);
+                    outcome.failed.push(entry.index);
+                }
+            }
+
+            entry.key = key;
+            self.metadata.insert(key, entry);
+        }
+
+        if self.config.embedding_model.is_none() {
+            self.config.embedding_model = Some(self.embedder.model().to_string());
+            self.config.embedding_dim = embeddings.iter().flatten().next().map(Vec::len);
+            self.save_recorded_config()?;
+        }
+        self.persist()?;
+        Ok(outcome)
+    }
+
+    /// Make `embedding` the only vector stored under `key` (idempotent upsert).
+    ///
+    /// Without `multi`, usearch rejects a second vector for a key, so a key without
+    /// metadata (new) is added directly and only a known key, or a dangling vector left
+    /// by a crash (the add fails), needs the remove. With `multi` the add would succeed
+    /// and stack vectors, so the key is always cleared first.
+    fn replace_vector(&self, key: u64, embedding: &[f32]) -> Result<(), StoreError> {
+        if self.config.multi || self.metadata.contains_key(&key) {
+            let _ = self.index.remove(key);
+        }
+        if self.index.add(key, embedding).is_err() {
+            let _ = self.index.remove(key);
+            self.index
+                .add(key, embedding)
+                .map_err(|e| StoreError::Index(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// Indices of a video's segments that are stored without a vector because their
+    /// embedding failed (see `store_transcript`), in order.
+    pub fn failed_segments(&self, video_id: &str) -> Vec<usize> {
+        let mut failed: Vec<usize> = self
+            .metadata
+            .values()
+            .filter(|seg| seg.video_id == video_id && !self.index.contains(seg.key))
+            .map(|seg| seg.index)
+            .collect();
+        failed.sort_unstable();
+        failed
+    }
+
+    /// Videos (and document namespaces) with at least one failed segment, sorted.
+    pub fn videos_with_failed_segments(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
             .metadata
             .values()
+            .filter(|seg| !self.index.contains(seg.key))
             .map(|seg| seg.video_id.clone())
             .collect::<std::collections::HashSet<_>>()
             .into_iter()
@@ -302,8 +1353,483 @@ impl VectorStore {
         ids
     }
 
+    /// Embed the given segments of a video again, e.g. the `failed` ones from
+    /// `store_transcript` once Ollama is healthy. Indices that aren't stored, or that
+    /// already have a vector, are ignored.
+    pub fn retry_failed(
+        &mut self,
+        video_id: &str,
+        indices: &[usize],
+    ) -> Result<StoreOutcome, StoreError> {
+        let entries: Vec<StoredSegment> = indices
+            .iter()
+            .filter_map(|&i| self.segment_at(video_id, i))
+            .filter(|seg| !self.index.contains(seg.key))
+            .cloned()
+            .collect();
+        self.upsert(entries)
+    }
+
+    /// Store free-text documents under `namespace`, replacing whatever documents it
+    /// held before. They share the index with transcripts, so `search` finds both
+    /// unless its filter says otherwise; `search_documents` returns only documents.
+    /// Blank documents are skipped, and failed embeddings are handled as in
+    /// `store_transcript`. Fails if `namespace` is a stored video's ID.
+    pub fn store_documents(
+        &mut self,
+        namespace: &str,
+        documents: &[Document],
+    ) -> Result<StoreOutcome, StoreError> {
+        if self
+            .metadata
+            .values()
+            .any(|seg| seg.video_id == namespace && seg.document.is_none())
+        {
+            return Err(StoreError::NamespaceInUse(namespace.to_string()));
+        }
+
+        let entries: Vec<StoredSegment> = documents
+            .iter()
+            .filter(|doc| !doc.text.trim().is_empty())
+            .enumerate()
+            .map(|(i, doc)| StoredSegment {
+                video_id: namespace.to_string(),
+                index: i,
+                start: 0.0,
+                end: 0.0,
+                text: doc.text.clone(),
+                key: 0,
+                confidence: None,
+                document: Some(DocumentInfo {
+                    id: doc.id.clone(),
+                    meta: doc.meta.clone(),
+                }),
+                raw_text: None,
+            })
+            .collect();
+        let count = entries.len();
+        let outcome = self.upsert(entries)?;
+
+        // Drop documents left over from a larger earlier version of the namespace
+        self.truncate_video(namespace, count)?;
+        Ok(outcome)
+    }
+
+    /// Semantic search over documents only, optionally within one namespace.
+    pub fn search_documents(
+        &self,
+        query: &str,
+        n: usize,
+        namespace: Option<&str>,
+    ) -> Result<Vec<DocumentHit>, StoreError> {
+        let filter = SearchFilter {
+            video_id: namespace,
+            documents: Some(true),
+            ..Default::default()
+        };
+        let hits = self
+            .search_keys(query, n, filter)?
+            .into_iter()
+            .filter_map(|hit| {
+                let seg = hit.segment?;
+                let doc = seg.document?;
+                Some(DocumentHit {
+                    namespace: seg.video_id,
+                    id: doc.id,
+                    text: seg.text,
+                    meta: doc.meta,
+                    distance: hit.distance,
+                })
+            })
+            .collect();
+
+        Ok(hits)
+    }
+
+    /// Semantic search across stored segments.
+    pub fn search(
+        &self,
+        query: &str,
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<Vec<SearchResult>, StoreError> {
+        Ok(self.search_iter(query, n, filter)?.collect())
+    }
+
+    /// Like `search`, but yields hits best first as the caller consumes them. The query
+    /// is embedded and the index searched up front; each hit's metadata is only looked
+    /// up and copied when the iterator reaches it, so a caller that stops early (at a
+    /// distance threshold, a per-video cap, ...) never builds the results it discards.
+    pub fn search_iter<'s>(
+        &'s self,
+        query: &str,
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<impl Iterator<Item = SearchResult> + use<'s>, StoreError> {
+        let query_vec = if self.is_empty() { Vec::new() } else { self.embed_query(query)? };
+        self.search_iter_by_vector(&query_vec, n, filter)
+    }
+
+    /// Like `search_iter`, for an already-embedded query.
+    pub fn search_iter_by_vector<'s>(
+        &'s self,
+        query_vec: &[f32],
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<impl Iterator<Item = SearchResult> + use<'s>, StoreError> {
+        let (keys, distances) = self.nearest(query_vec, n, filter)?;
+        Ok(keys.into_iter().zip(distances).filter_map(|(key, distance)| {
+            let seg = self.metadata.get(&key)?;
+            Some(SearchResult {
+                video_id: seg.video_id.clone(),
+                index: seg.index,
+                start: seg.start,
+                end: seg.end,
+                text: seg.text.clone(),
+                distance,
+                confidence: seg.confidence,
+            })
+        }))
+    }
+
+    /// "More like this": the `n` segments nearest to segment `index` of `video_id`,
+    /// best first, leaving out the segment itself. Its stored vector is the query, so
+    /// nothing goes to Ollama unless its embedding failed, in which case its text is
+    /// embedded again for the search (the store isn't changed).
+    pub fn related(
+        &self,
+        video_id: &str,
+        index: usize,
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<Vec<SearchResult>, StoreError> {
+        let seg = self.segment_at(video_id, index).ok_or_else(|| {
+            if self.has_video(video_id) {
+                StoreError::SegmentNotFound { video_id: video_id.to_string(), index }
+            } else {
+                StoreError::VideoNotFound(video_id.to_string())
+            }
+        })?;
+
+        let mut vector = vec![0.0f32; self.index.dimensions()];
+        let found = self
+            .index
+            .get(seg.key, &mut vector)
+            .map_err(|e| StoreError::Index(e.to_string()))?;
+        if found == 0 {
+            let prefix = if self.config.task_prefixes { DOCUMENT_PREFIX } else { "" };
+            let texts = [seg.text.as_str()];
+            vector = self.embed_batched_with(&texts, prefix, &mut |_, _| {})?.remove(0);
+        }
+
+        let mut results: Vec<SearchResult> = self
+            .search_iter_by_vector(&vector, n + 1, filter)?
+            .filter(|r| !(r.video_id == seg.video_id && r.index == seg.index))
+            .collect();
+        results.truncate(n);
+        Ok(results)
+    }
+
+    /// Search, then widen each hit to the `window` segments on either side and join
+    /// them into a passage. Hits whose windows overlap or touch within a video are
+    /// merged, so fewer than `n` passages may come back. Ordered by best distance.
+    pub fn search_passages(
+        &self,
+        query: &str,
+        n: usize,
+        window: usize,
+    ) -> Result<Vec<Passage>, StoreError> {
+        let mut ranges: Vec<(String, usize, usize, f32)> = self
+            .search(query, n, SearchFilter::default())?
+            .into_iter()
+            .map(|r| {
+                let lo = r.index.saturating_sub(window);
+                (r.video_id, lo, r.index.saturating_add(window), r.distance)
+            })
+            .collect();
+
+        ranges.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+        let mut merged: Vec<(String, usize, usize, f32)> = Vec::new();
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.0 == range.0 && range.1 <= last.2.saturating_add(1) => {
+                    last.2 = last.2.max(range.2);
+                    last.3 = last.3.min(range.3);
+                }
+                _ => merged.push(range),
+            }
+        }
+        merged.sort_by(|a, b| a.3.total_cmp(&b.3));
+
+        let passages = merged
+            .into_iter()
+            .filter_map(|(video_id, lo, hi, distance)| {
+                // A huge window stops at the video's last segment
+                let end = hi.min(self.next_index(&video_id).saturating_sub(1));
+                let segments: Vec<&StoredSegment> = (lo..=end)
+                    .filter_map(|i| self.segment_at(&video_id, i))
+                    .collect();
+                let (first, last) = (segments.first()?, segments.last()?);
+                Some(Passage {
+                    start: first.start,
+                    end: last.end,
+                    text: segments
+                        .iter()
+                        .map(|s| s.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    distance,
+                    video_id,
+                })
+            })
+            .collect();
+
+        Ok(passages)
+    }
+
+    /// Number of vectors in the index.
+    pub fn len(&self) -> usize {
+        self.index.size()
+    }
+
+    /// Whether the index holds no vectors.
+    pub fn is_empty(&self) -> bool {
+        self.index.size() == 0
+    }
+
+    /// Nearest-neighbour search returning raw index keys, including keys that
+    /// have no metadata entry (which `search` silently drops).
+    pub fn search_keys(
+        &self,
+        query: &str,
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<Vec<KeyMatch>, StoreError> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query_vec = self.embed_query(query)?;
+        self.search_keys_by_vector(&query_vec, n, filter)
+    }
+
+    /// Embed a search query with the store's embedding model, prefixed if the store
+    /// uses task prefixes. The vector can be reused for any store with the same model
+    /// and the same `task_prefixes` setting.
+    pub fn embed_query(&self, query: &str) -> Result<Vec<f32>, StoreError> {
+        self.check_cancelled()?;
+        let embedding = if self.config.task_prefixes {
+            self.embedder.embed_query(&format!("{QUERY_PREFIX}{query}"))?
+        } else {
+            self.embedder.embed_query(query)?
+        };
+        self.check_cancelled()?;
+        self.check_dimension(&embedding)?;
+        Ok(embedding)
+    }
+
+    /// Like `search_keys`, for an already-embedded query.
+    pub fn search_keys_by_vector(
+        &self,
+        query_vec: &[f32],
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<Vec<KeyMatch>, StoreError> {
+        let (keys, distances) = self.nearest(query_vec, n, filter)?;
+        let hits = keys
+            .into_iter()
+            .zip(distances)
+            .map(|(key, distance)| KeyMatch {
+                key,
+                distance,
+                segment: self.metadata.get(&key).cloned(),
+            })
+            .collect();
+
+        Ok(hits)
+    }
+
+    /// The `n` nearest index keys matching `filter` and their distances, best first.
+    fn nearest(
+        &self,
+        query_vec: &[f32],
+        n: usize,
+        filter: SearchFilter,
+    ) -> Result<(Vec<u64>, Vec<f32>), StoreError> {
+        if self.is_empty() {
+            return Ok((Vec::new(), Vec::new()));
+        }
+        // Never ask usearch for more neighbours than there are vectors
+        let n = n.min(self.len());
+
+        let matches = if filter.is_empty() {
+            self.index.search(query_vec, n)
+        } else {
+            self.index.filtered_search(query_vec, n, |key| {
+                self.metadata
+                    .get(&key)
+                    .is_some_and(|seg| self.matches_filter(seg, filter))
+            })
+        }
+        .map_err(|e| StoreError::Index(e.to_string()))?;
+
+        Ok((matches.keys, matches.distances))
+    }
+
+    fn matches_filter(&self, seg: &StoredSegment, filter: SearchFilter) -> bool {
+        filter.video_id.is_none_or(|vid| seg.video_id == vid)
+            && filter
+                .documents
+                .is_none_or(|documents| seg.document.is_some() == documents)
+            && filter
+                .language
+                .is_none_or(|lang| self.video_in_language(&seg.video_id, lang))
+            && filter.tag.is_none_or(|tag| self.video_has_tag(&seg.video_id, tag))
+    }
+
+    /// Whether `video_id` carries `tag` (case-insensitive).
+    pub fn video_has_tag(&self, video_id: &str, tag: &str) -> bool {
+        self.videos
+            .get(video_id)
+            .is_some_and(|v| v.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+    }
+
+    /// Whether `video_id` was recorded as transcribed in `language` (case-insensitive).
+    fn video_in_language(&self, video_id: &str, language: &str) -> bool {
+        self.videos
+            .get(video_id)
+            .and_then(|v| v.language.as_deref())
+            .is_some_and(|l| l.eq_ignore_ascii_case(language))
+    }
+
+    /// Whether any stored video was transcribed in `language` (case-insensitive).
+    pub fn has_language(&self, language: &str) -> bool {
+        self.videos
+            .keys()
+            .any(|id| self.has_video(id) && self.video_in_language(id, language))
+    }
+
+    /// Whether any stored video carries `tag` (case-insensitive).
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.videos
+            .keys()
+            .any(|id| self.has_video(id) && self.video_has_tag(id, tag))
+    }
+
+    /// Get all segments for a video, sorted by start time. `video_id` may be a unique
+    /// prefix of the stored ID; see `resolve_video_id`.
+    pub fn get_segments(&self, video_id: &str) -> Result<Vec<StoredSegment>, StoreError> {
+        video_segments(&self.metadata, &self.resolve_video_id(video_id)?)
+    }
+
+    /// The stored video ID that `prefix` abbreviates, like git's short hashes. A full
+    /// ID always resolves to itself, even if it is also the prefix of a longer one.
+    /// Fails with `VideoNotFound` if nothing matches and `AmbiguousVideoId` (listing
+    /// the candidates) if several videos do.
+    pub fn resolve_video_id(&self, prefix: &str) -> Result<String, StoreError> {
+        if self.has_video(prefix) {
+            return Ok(prefix.to_string());
+        }
+        let mut candidates: Vec<String> = match prefix {
+            "" => Vec::new(),
+            _ => self
+                .get_video_ids()
+                .into_iter()
+                .filter(|id| id.starts_with(prefix))
+                .collect(),
+        };
+        match candidates.len() {
+            0 => Err(StoreError::VideoNotFound(prefix.to_string())),
+            1 => Ok(candidates.swap_remove(0)),
+            _ => Err(StoreError::AmbiguousVideoId {
+                prefix: prefix.to_string(),
+                candidates,
+            }),
+        }
+    }
+
+    /// List all stored video IDs, sorted. Document namespaces aren't videos and are
+    /// left out; see `document_namespaces`.
+    pub fn get_video_ids(&self) -> Vec<String> {
+        self.namespaces(false)
+    }
+
+    /// Namespaces holding documents stored with `store_documents`, sorted.
+    pub fn document_namespaces(&self) -> Vec<String> {
+        self.namespaces(true)
+    }
+
+    fn namespaces(&self, documents: bool) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .metadata
+            .values()
+            .filter(|seg| seg.document.is_some() == documents)
+            .map(|seg| seg.video_id.clone())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// The segment stored at `index` for a video, looked up by its deterministic key.
+    pub fn segment_at(&self, video_id: &str, index: usize) -> Option<&StoredSegment> {
+        let is_it = |seg: &StoredSegment| seg.video_id == video_id && seg.index == index;
+        for key in candidate_keys(video_id, index) {
+            match self.metadata.get(&key) {
+                Some(seg) if is_it(seg) => return Some(seg),
+                Some(_) => continue,
+                // Deleting a segment can leave a gap before a salted key, so scan to be sure
+                None => return self.metadata.values().find(|seg| is_it(seg)),
+            }
+        }
+        None
+    }
+
+    /// Key to store segment `index` of `video_id` under: the first candidate that is
+    /// free or already holds this segment. Without this, two segments whose keys
+    /// collide would silently overwrite each other.
+    fn segment_key(&self, video_id: &str, index: usize) -> u64 {
+        candidate_keys(video_id, index)
+            .find(|key| {
+                self.metadata
+                    .get(key)
+                    .is_none_or(|seg| seg.video_id == video_id && seg.index == index)
+            })
+            .expect("candidate keys are unbounded")
+    }
+
+    /// A copy of one stored segment, or `None` if the video has no segment at `index`.
+    pub fn get_segment(&self, video_id: &str, index: usize) -> Option<StoredSegment> {
+        self.segment_at(video_id, index).cloned()
+    }
+
+    /// The YouTube video ID behind a stored name: its recorded source, or the name itself.
+    pub fn source_id<'a>(&'a self, video_id: &'a str) -> &'a str {
+        self.videos
+            .get(video_id)
+            .and_then(|v| v.source_id.as_deref())
+            .unwrap_or(video_id)
+    }
+
+    /// Whether any segments are stored for a video. Reads metadata only.
+    pub fn has_video(&self, video_id: &str) -> bool {
+        self.metadata.values().any(|seg| seg.video_id == video_id)
+    }
+
+    /// Number of stored segments for a video (0 if it isn't stored). Reads metadata only.
+    pub fn segment_count(&self, video_id: &str) -> usize {
+        self.metadata
+            .values()
+            .filter(|seg| seg.video_id == video_id)
+            .count()
+    }
+
     /// Remove all segments for a video.
     pub fn delete_video(&mut self, video_id: &str) -> Result<usize, StoreError> {
+        if self.read_only {
+            return Err(StoreError::ReadOnly);
+        }
         let keys_to_remove: Vec<u64> = self
             .metadata
             .values()
@@ -319,25 +1845,342 @@ impl VectorStore {
             let _ = self.index.remove(*key);
             self.metadata.remove(key);
         }
+        self.videos.remove(video_id);
 
         self.persist()?;
         Ok(keys_to_remove.len())
     }
 
-    /// Write index and metadata to disk.
+    /// Remove a video's segments at index `len` and above, e.g. the tail of a longer
+    /// transcript stored before. Returns how many were removed.
+    pub fn truncate_video(&mut self, video_id: &str, len: usize) -> Result<usize, StoreError> {
+        let stale: Vec<u64> = self
+            .metadata
+            .values()
+            .filter(|seg| seg.video_id == video_id && seg.index >= len)
+            .map(|seg| seg.key)
+            .collect();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+        if self.read_only {
+            return Err(StoreError::ReadOnly);
+        }
+
+        for key in &stale {
+            let _ = self.index.remove(*key);
+            self.metadata.remove(key);
+        }
+        self.persist()?;
+        Ok(stale.len())
+    }
+
+    /// Record title, upload date etc. for a video, replacing any previous info.
+    pub fn set_video_info(&mut self, video_id: &str, info: VideoInfo) -> Result<(), StoreError> {
+        self.videos.insert(video_id.to_string(), info);
+        self.persist()
+    }
+
+    /// Stored info for a video, if any was recorded.
+    pub fn video_info(&self, video_id: &str) -> Option<&VideoInfo> {
+        self.videos.get(video_id)
+    }
+
+    /// Stored video IDs whose upload date falls within `since..=until` (`YYYY-MM-DD`,
+    /// either bound optional). With a bound set, videos without a known date are excluded.
+    pub fn get_video_ids_uploaded(&self, since: Option<&str>, until: Option<&str>) -> Vec<String> {
+        self.get_video_ids()
+            .into_iter()
+            .filter(|id| {
+                if since.is_none() && until.is_none() {
+                    return true;
+                }
+                let Some(date) = self.videos.get(id).and_then(|v| v.upload_date.as_deref()) else {
+                    return false;
+                };
+                // ISO dates compare correctly as strings
+                since.is_none_or(|s| date >= s) && until.is_none_or(|u| date <= u)
+            })
+            .collect()
+    }
+
+    /// Compare index keys against metadata keys and report any mismatch.
+    pub fn verify(&self) -> Result<VerifyReport, StoreError> {
+        let mut index_only: Vec<u64> = self
+            .index_keys()?
+            .into_iter()
+            .filter(|key| !self.metadata.contains_key(key))
+            .collect();
+        index_only.sort_unstable();
+
+        let mut unembedded: Vec<u64> = self
+            .metadata
+            .keys()
+            .copied()
+            .filter(|&key| !self.index.contains(key))
+            .collect();
+        unembedded.sort_unstable();
+
+        Ok(VerifyReport {
+            index_only,
+            unembedded,
+        })
+    }
+
+    /// Drop dangling vectors (`index_only`), then persist. Segments without a vector
+    /// are left for `retry_failed`. Returns the report the repair was based on.
+    pub fn repair(&mut self) -> Result<VerifyReport, StoreError> {
+        let report = self.verify()?;
+        if report.index_only.is_empty() {
+            return Ok(report);
+        }
+
+        for key in &report.index_only {
+            self.index
+                .remove(*key)
+                .map_err(|e| StoreError::Index(e.to_string()))?;
+        }
+
+        self.persist()?;
+        Ok(report)
+    }
+
+    /// Enumerate every key in the index. usearch has no key iterator, so this runs
+    /// an exhaustive exact search with `count = size`, which visits all vectors.
+    fn index_keys(&self) -> Result<Vec<u64>, StoreError> {
+        let size = self.index.size();
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut probe = vec![0.0f32; EMBEDDING_DIM];
+        probe[0] = 1.0;
+        let matches = self
+            .index
+            .exact_search(&probe, size)
+            .map_err(|e| StoreError::Index(e.to_string()))?;
+
+        Ok(matches.keys)
+    }
+
+    /// Write index and metadata to disk, each file replaced atomically. A no-op after
+    /// `set_persist(false)`.
     fn persist(&self) -> Result<(), StoreError> {
+        if self.read_only {
+            return Err(StoreError::ReadOnly);
+        }
+        if !self.persist_changes {
+            return Ok(());
+        }
+        let index_path = self.data_dir.join(INDEX_FILE);
+        let tmp = temp_path(&index_path);
         self.index
-            .save(
-                self.data_dir
-                    .join(INDEX_FILE)
-                    .to_str()
-                    .unwrap_or_default(),
-            )
+            .save(tmp.to_str().unwrap_or_default())
             .map_err(|e| StoreError::Index(e.to_string()))?;
+        fs::rename(&tmp, &index_path)?;
 
-        let json = serde_json::to_string_pretty(&self.metadata)?;
-        fs::write(self.data_dir.join(METADATA_FILE), json)?;
+        let format = self.config.metadata_format;
+        let contents = match format {
+            MetadataFormat::Json => serde_json::to_string_pretty(&self.metadata)?.into_bytes(),
+            MetadataFormat::Binary => encode_metadata(&self.metadata)?,
+        };
+        write_atomic(&self.data_dir.join(format.file_name()), contents)?;
+        let stale = self.data_dir.join(format.other().file_name());
+        if stale.exists() {
+            fs::remove_file(stale)?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.videos)?;
+        write_atomic(&self.data_dir.join(VIDEOS_FILE), json)?;
 
         Ok(())
     }
 }
+
+/// `path` with `.tmp` appended, next to it so a rename stays on one filesystem.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+/// Lock `data_dir`'s lock file, failing with `Locked` if another writer holds it.
+fn lock_store(data_dir: &Path) -> Result<fs::File, StoreError> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(data_dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(fs::TryLockError::WouldBlock) => {
+            Err(StoreError::Locked(data_dir.display().to_string()))
+        }
+        Err(fs::TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+/// Write through a temporary file and rename it over `path`, so a concurrent reader
+/// sees the old contents or the new ones, never a partial file.
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty store for a temporary directory that is never created.
+    fn scratch_store(name: &str) -> (PathBuf, VectorStore) {
+        let dir = std::env::temp_dir().join(format!("sawtrs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let vs = VectorStore::open_unpersisted(dir.to_str().unwrap(), IndexTuning::default());
+        (dir, vs.unwrap())
+    }
+
+    fn unit_vectors(n: usize) -> Vec<Option<Vec<f32>>> {
+        (0..n)
+            .map(|i| {
+                let mut v = vec![0.0; EMBEDDING_DIM];
+                v[i] = 1.0;
+                Some(v)
+            })
+            .collect()
+    }
+
+    fn segment(video_id: &str, index: usize, key: u64) -> StoredSegment {
+        StoredSegment {
+            video_id: video_id.to_string(),
+            index,
+            start: index as f64,
+            end: index as f64 + 1.0,
+            text: format!("segment {index}"),
+            key,
+            confidence: None,
+            document: None,
+            raw_text: None,
+        }
+    }
+
+    #[test]
+    fn colliding_key_falls_back_to_salted_candidate() {
+        let (_, mut vs) = scratch_store("collision");
+        let mut candidates = candidate_keys("abc", 0);
+        let (plain, salted) = (candidates.next().unwrap(), candidates.next().unwrap());
+        assert_ne!(plain, salted);
+
+        // Another segment already holds the plain key of `abc` #0
+        vs.metadata.insert(plain, segment("other", 7, plain));
+        assert_eq!(vs.segment_key("abc", 0), salted);
+
+        // Once stored there, the segment keeps its salted key and is found under it
+        vs.metadata.insert(salted, segment("abc", 0, salted));
+        assert_eq!(vs.segment_key("abc", 0), salted);
+        assert_eq!(vs.segment_at("abc", 0).map(|seg| seg.key), Some(salted));
+        assert_eq!(vs.segment_at("other", 7).map(|seg| seg.key), Some(plain));
+    }
+
+    #[test]
+    fn storing_a_video_again_replaces_its_vectors() {
+        let (_, mut vs) = scratch_store("restore");
+        let embeddings = unit_vectors(3);
+        let entries = |n| (0..n).map(|i| segment("abc", i, 0)).collect::<Vec<_>>();
+
+        vs.insert_embedded(entries(3), &embeddings).unwrap();
+        assert_eq!(vs.index.size(), 3);
+        let outcome = vs.insert_embedded(entries(3), &embeddings).unwrap();
+        assert_eq!(outcome.stored, 3);
+        assert_eq!(vs.index.size(), 3);
+        assert_eq!(vs.len(), 3);
+
+        // A shorter transcript, stored the way `store_transcript` does it
+        let outcome = vs.insert_embedded(entries(2), &embeddings[..2]).unwrap();
+        assert_eq!(vs.truncate_video("abc", outcome.stored).unwrap(), 1);
+        assert_eq!(vs.len(), 2);
+        assert_eq!(vs.segment_count("abc"), 2);
+        assert_eq!(vs.next_index("abc"), 2);
+    }
+
+    #[test]
+    fn repair_keeps_segments_that_failed_to_embed() {
+        let (_, mut vs) = scratch_store("repair");
+        let mut embeddings = unit_vectors(2);
+        embeddings[1] = None;
+        let entries = (0..2).map(|i| segment("abc", i, 0)).collect();
+        vs.insert_embedded(entries, &embeddings).unwrap();
+        // A vector without metadata, as a crash between the two writes leaves it
+        vs.index.reserve(3).unwrap();
+        vs.index.add(42, unit_vectors(3)[2].as_deref().unwrap()).unwrap();
+
+        let report = vs.repair().unwrap();
+        assert_eq!(report.index_only, vec![42]);
+        assert_eq!(report.unembedded.len(), 1);
+        assert!(!vs.index.contains(42));
+        assert_eq!(vs.failed_segments("abc"), vec![1]);
+        assert_eq!(vs.metadata.len(), 2);
+    }
+
+    #[test]
+    fn second_writer_is_locked_out() {
+        let dir = std::env::temp_dir().join(format!("sawtrs-lock-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.to_str().unwrap();
+
+        let writer = VectorStore::open(path).unwrap();
+        assert!(matches!(VectorStore::open(path), Err(StoreError::Locked(_))));
+        assert!(VectorStore::open_read_only(path, IndexTuning::default()).is_ok());
+        drop(writer);
+        assert!(VectorStore::open(path).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn binary_metadata_round_trips() {
+        let mut with_extras = segment("abc", 1, 2);
+        with_extras.confidence = Some(0.5);
+        with_extras.raw_text = Some(" segment 1".to_string());
+        let mut document = segment("notes", 0, 3);
+        let meta = serde_json::json!({"tags": ["a"]});
+        document.document = Some(DocumentInfo { id: "todo".to_string(), meta: meta.clone() });
+        let metadata: HashMap<u64, StoredSegment> = [segment("abc", 0, 1), with_extras, document]
+            .into_iter()
+            .map(|seg| (seg.key, seg))
+            .collect();
+
+        let data = encode_metadata(&metadata).unwrap();
+        assert!(data.starts_with(BINARY_METADATA_MAGIC));
+        let decoded = decode_metadata(&data).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[&2].confidence, Some(0.5));
+        assert_eq!(decoded[&2].raw_text.as_deref(), Some(" segment 1"));
+        assert_eq!(decoded[&1].raw_text, None);
+        let doc = decoded[&3].document.as_ref().unwrap();
+        assert_eq!((doc.id.as_str(), &doc.meta), ("todo", &meta));
+
+        assert!(decode_metadata(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn embeddings_of_another_dimension_are_rejected() {
+        let (_, mut vs) = scratch_store("dimension");
+        let result = vs.insert_embedded(vec![segment("abc", 0, 0)], &[Some(vec![1.0; 4])]);
+        assert!(matches!(
+            result,
+            Err(StoreError::DimensionMismatch { expected: EMBEDDING_DIM, actual: 4, .. })
+        ));
+        assert!(vs.is_empty());
+    }
+
+    #[test]
+    fn unpersisted_store_writes_nothing() {
+        let (dir, mut vs) = scratch_store("unpersisted");
+        let entries = (0..2).map(|i| segment("abc", i, 0)).collect();
+        vs.insert_embedded(entries, &unit_vectors(2)).unwrap();
+        vs.set_video_info("abc", VideoInfo::default()).unwrap();
+
+        assert_eq!(vs.len(), 2);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,57 @@
+/// Format seconds as a clock timestamp: `MM:SS.ss`, or `H:MM:SS.ss` from one hour up.
+///
+/// Rounds to centiseconds before splitting, so 59.999 becomes `01:00.00` rather
+/// than `00:60.00`. Negative inputs are treated as 0.
+pub fn format_clock(seconds: f64) -> String {
+    let total_cs = (seconds.max(0.0) * 100.0).round() as u64;
+    let hours = total_cs / 360_000;
+    let mins = (total_cs / 6_000) % 60;
+    let secs = (total_cs / 100) % 60;
+    let cs = total_cs % 100;
+
+    if hours > 0 {
+        format!("{hours}:{mins:02}:{secs:02}.{cs:02}")
+    } else {
+        format!("{mins:02}:{secs:02}.{cs:02}")
+    }
+}
+
+/// Format seconds as a subtitle timecode, `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm`
+/// (WebVTT) depending on `millis_sep`. Negative inputs are treated as 0.
+pub fn format_timecode(seconds: f64, millis_sep: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let mins = (total_ms / 60_000) % 60;
+    let secs = (total_ms / 1000) % 60;
+    let ms = total_ms % 1000;
+    format!("{hours:02}:{mins:02}:{secs:02}{millis_sep}{ms:03}")
+}
+
+/// Parse `SS`, `MM:SS` or `H:MM:SS` (seconds may be fractional) into seconds.
+///
+/// Returns `None` for empty, negative or malformed input, or when minutes or
+/// seconds are out of range in the multi-part forms.
+pub fn parse_clock(s: &str) -> Option<f64> {
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        return None;
+    }
+
+    let (secs_part, whole) = parts.split_last()?;
+    let secs: f64 = secs_part.parse().ok()?;
+    if !secs.is_finite() || secs < 0.0 || (!whole.is_empty() && secs >= 60.0) {
+        return None;
+    }
+
+    let mut total = secs;
+    for (i, part) in whole.iter().rev().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        // minutes must be < 60 when preceded by hours
+        if i == 0 && whole.len() == 2 && value >= 60 {
+            return None;
+        }
+        total += value as f64 * 60f64.powi(i as i32 + 1);
+    }
+
+    Some(total)
+}
@@ -1,12 +1,39 @@
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use crate::time::format_clock;
+use whisper_rs::{
+    FullParams, SamplingStrategy, SegmentCallbackData, WhisperContext, WhisperContextParameters,
+    WhisperSegment, WhisperState, WhisperTokenId,
+};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct Segment {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    /// Mean probability of the segment's text tokens (0.0–1.0). `None` if it has none.
+    pub confidence: Option<f32>,
+    /// Whisper's text exactly as decoded, when `text` differs from it (e.g. trimmed).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_text: Option<String>,
+}
+
+impl Segment {
+    /// Build a segment from whisper's text, keeping the original if cleaning changed it.
+    fn from_whisper(start: f64, end: f64, raw: &str, confidence: Option<f32>) -> Self {
+        let text = raw.trim().to_string();
+        Segment {
+            start,
+            end,
+            raw_text: (text != raw).then(|| raw.to_string()),
+            text,
+            confidence,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -22,39 +49,184 @@ pub enum TranscribeError {
 }
 
 const DEFAULT_MODEL_DIR: &str = "models";
-const DEFAULT_MODEL_NAME: &str = "whisper-large-v3-turbo.bin"; // ggml model for whisper.cpp
+pub const DEFAULT_MODEL_NAME: &str = "whisper-large-v3-turbo.bin"; // ggml model for whisper.cpp
 //const DEFAULT_MODEL_NAME: &str = "ggml-large-v3.bin"; // ggml model for whisper.cpp
+/// Upper bound on concurrent whisper states in `transcribe_many`; each holds its own
+/// decoder buffers plus the file's samples, so memory grows linearly with jobs.
+pub const MAX_JOBS: usize = 4;
+/// whisper.cpp only accepts 16kHz input.
+const SAMPLE_RATE: u32 = 16_000;
+/// How many runner-up languages `Transcript::language_candidates` keeps.
+const LANGUAGE_CANDIDATES: usize = 3;
+/// Audio scored for `score_languages` without a detect window; whisper itself
+/// detects on the first 30 seconds.
+const SCORE_WINDOW_SECONDS: f64 = 30.0;
+
+/// RMS level quiet recordings are raised to when normalization is enabled.
+const NORMALIZE_TARGET_RMS_DBFS: f32 = -20.0;
+/// Gain is capped so the loudest sample never exceeds this level.
+const NORMALIZE_PEAK_CEILING_DBFS: f32 = -1.0;
 
-/// Resolve the model path: use provided path or fall back to `models/ggml-large-v3.bin`.
-fn resolve_model_path(model_path: Option<&str>) -> Result<String, TranscribeError> {
+/// Environment variable overriding the default model: a bare filename resolved under
+/// the model directory, or a path.
+pub const MODEL_ENV_VAR: &str = "SAWT_MODEL";
+/// Environment variable overriding the model directory (`models/`).
+pub const MODEL_DIR_ENV_VAR: &str = "SAWT_MODEL_DIR";
+
+/// Directory bare model names resolve under: the provided one, then `$SAWT_MODEL_DIR`,
+/// then `models/`.
+fn resolve_model_dir(model_dir: Option<&str>) -> String {
+    model_dir
+        .map(str::to_string)
+        .or_else(|| std::env::var(MODEL_DIR_ENV_VAR).ok())
+        .map(|dir| dir.trim().to_string())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| DEFAULT_MODEL_DIR.to_string())
+}
+
+/// Resolve the model path: use the provided path, then `$SAWT_MODEL`, then fall back to
+/// `whisper-large-v3-turbo.bin` in the model directory.
+fn resolve_model_path(
+    model_path: Option<&str>,
+    model_dir: Option<&str>,
+) -> Result<String, TranscribeError> {
     if let Some(p) = model_path {
         if Path::new(p).exists() {
             return Ok(p.to_string());
         }
-        return Err(TranscribeError::ModelNotFound(p.to_string()));
+        return Err(TranscribeError::ModelNotFound(format!("{p} (from model path option)")));
     }
 
-    let default = format!("{DEFAULT_MODEL_DIR}/{DEFAULT_MODEL_NAME}");
+    if let Ok(name) = std::env::var(MODEL_ENV_VAR)
+        && !name.trim().is_empty()
+    {
+        let name = name.trim();
+        // A bare filename lives in the models directory; anything with a separator is a path
+        if Path::new(name).components().count() > 1 {
+            if Path::new(name).exists() {
+                return Ok(name.to_string());
+            }
+            return Err(TranscribeError::ModelNotFound(format!(
+                "{name} (from ${MODEL_ENV_VAR})"
+            )));
+        }
+        let dir = resolve_model_dir(model_dir);
+        let candidate = Path::new(&dir).join(name).display().to_string();
+        if Path::new(&candidate).exists() {
+            return Ok(candidate);
+        }
+        return Err(TranscribeError::ModelNotFound(format!(
+            "{candidate} (from ${MODEL_ENV_VAR}={name}, searched {dir})"
+        )));
+    }
+
+    let dir = resolve_model_dir(model_dir);
+    let default = Path::new(&dir).join(DEFAULT_MODEL_NAME).display().to_string();
     if Path::new(&default).exists() {
         return Ok(default);
     }
 
     Err(TranscribeError::ModelNotFound(format!(
-        "{default} (download a ggml model from https://github.com/ggml-org/whisper.cpp)"
+        "{default} (built-in default, searched {dir}; set ${MODEL_ENV_VAR} to override, or \
+         download a ggml model from https://github.com/ggml-org/whisper.cpp)"
     )))
 }
 
-/// Read a WAV file and return mono f32 samples at 16kHz.
-fn read_wav(path: &str) -> Result<Vec<f32>, TranscribeError> {
-    let reader = hound::WavReader::open(path)
-        .map_err(|e| TranscribeError::Wav(format!("{path}: {e}")))?;
+/// The model file `transcribe` would load with these options, or why none was found.
+pub fn model_path(opts: &TranscribeOptions) -> Result<String, TranscribeError> {
+    resolve_model_path(opts.model_path, opts.model_dir)
+}
+
+/// Version of whisper.cpp compiled into this build.
+pub fn whisper_version() -> &'static str {
+    whisper_rs::get_whisper_version()
+}
+
+/// The format of a WAV file, as far as whisper cares.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WavInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    /// IEEE float samples rather than integer PCM.
+    pub float: bool,
+    /// Length in seconds.
+    pub duration: f64,
+}
+
+impl WavInfo {
+    /// 16 kHz mono: whisper takes the samples without downmixing, and the rate is the
+    /// one it assumes. The sample format doesn't matter, every one is converted.
+    pub fn is_optimal(&self) -> bool {
+        self.sample_rate == SAMPLE_RATE && self.channels == 1
+    }
+
+    /// Whether whisper will misread the audio's speed (see `Transcript::input_warning`).
+    pub fn wrong_rate(&self) -> bool {
+        self.sample_rate != SAMPLE_RATE
+    }
+}
+
+/// Read just the header of a WAV file, to explain what `transcribe` will do with it
+/// before a long run. 64-bit float WAVs, which hound can't open, are read in full.
+pub fn inspect_wav(path: &str) -> Result<WavInfo, TranscribeError> {
+    match hound::WavReader::open(path) {
+        Ok(reader) => {
+            let spec = reader.spec();
+            Ok(WavInfo {
+                sample_rate: spec.sample_rate,
+                channels: spec.channels,
+                bits_per_sample: spec.bits_per_sample,
+                float: spec.sample_format == hound::SampleFormat::Float,
+                duration: reader.duration() as f64 / spec.sample_rate.max(1) as f64,
+            })
+        }
+        Err(hound::Error::FormatError(msg)) => {
+            let (samples, channels, rate) =
+                read_f64_wav(path).map_err(|_| TranscribeError::Wav(format!("{path}: {msg}")))?;
+            let frames = samples.len() / usize::from(channels.max(1));
+            Ok(WavInfo {
+                sample_rate: rate,
+                channels,
+                bits_per_sample: 64,
+                float: true,
+                duration: frames as f64 / rate.max(1) as f64,
+            })
+        }
+        Err(e) => Err(TranscribeError::Wav(format!("{path}: {e}"))),
+    }
+}
+
+/// Read a WAV file and return mono f32 samples, with a warning if they aren't 16kHz.
+fn read_wav(path: &str) -> Result<(Vec<f32>, Option<String>), TranscribeError> {
+    let reader = match hound::WavReader::open(path) {
+        Ok(reader) => reader,
+        // hound only reads 32-bit floats; try the 64-bit float layout before giving up
+        Err(hound::Error::FormatError(msg)) => {
+            let (samples, channels, rate) =
+                read_f64_wav(path).map_err(|_| TranscribeError::Wav(format!("{path}: {msg}")))?;
+            let warning = sample_rate_warning(path, rate);
+            return Ok((downmix(rescale_float(samples), channels), warning));
+        }
+        Err(e) => return Err(TranscribeError::Wav(format!("{path}: {e}"))),
+    };
 
     let spec = reader.spec();
+    let warning = sample_rate_warning(path, spec.sample_rate);
+    // WAVE_FORMAT_EXTENSIBLE files get past hound's header check, but it still can't
+    // decode anything but 32-bit floats
+    if spec.sample_format == hound::SampleFormat::Float && spec.bits_per_sample == 64 {
+        let (samples, channels, _) =
+            read_f64_wav(path).map_err(|e| TranscribeError::Wav(format!("{path}: {e}")))?;
+        return Ok((downmix(rescale_float(samples), channels), warning));
+    }
     let samples: Vec<f32> = match spec.sample_format {
-        hound::SampleFormat::Float => reader
-            .into_samples::<f32>()
-            .filter_map(Result::ok)
-            .collect(),
+        hound::SampleFormat::Float => rescale_float(
+            reader
+                .into_samples::<f32>()
+                .filter_map(Result::ok)
+                .collect(),
+        ),
         hound::SampleFormat::Int => {
             let max_val = (1u32 << (spec.bits_per_sample - 1)) as f32;
             reader
@@ -65,63 +237,660 @@ fn read_wav(path: &str) -> Result<Vec<f32>, TranscribeError> {
         }
     };
 
-    // Convert to mono if stereo
-    let mono = if spec.channels == 2 {
-        samples
-            .chunks(2)
-            .map(|chunk| (chunk[0] + chunk.get(1).copied().unwrap_or(0.0)) / 2.0)
-            .collect()
+    Ok((downmix(samples, spec.channels), warning))
+}
+
+/// whisper.cpp takes the samples as 16 kHz regardless of the header, so other rates
+/// transcribe as sped-up or slowed-down speech. Nothing is resampled; the caller
+/// gets this warning in `Transcript::input_warning`.
+fn sample_rate_warning(path: &str, rate: u32) -> Option<String> {
+    (rate != SAMPLE_RATE).then(|| {
+        format!(
+            "{path}: input is {rate}Hz; whisper expects {SAMPLE_RATE}Hz, so the \
+             transcription may be wrong. Convert it first: ffmpeg -i {path} -ar {SAMPLE_RATE} \
+             -ac 1 out.wav"
+        )
+    })
+}
+
+/// Float WAVs aren't guaranteed to stay within [-1, 1]; whisper expects them to.
+/// If the peak exceeds 1.0 the whole buffer is scaled down by it, and non-finite
+/// samples are zeroed.
+fn rescale_float(mut samples: Vec<f32>) -> Vec<f32> {
+    let peak = samples
+        .iter()
+        .filter(|s| s.is_finite())
+        .fold(0.0f32, |max, s| max.max(s.abs()));
+    let gain = if peak > 1.0 { 1.0 / peak } else { 1.0 };
+    for s in &mut samples {
+        *s = if s.is_finite() { (*s * gain).clamp(-1.0, 1.0) } else { 0.0 };
+    }
+    samples
+}
+
+/// Minimal reader for 64-bit IEEE float WAVs, which hound rejects. Returns the
+/// interleaved samples and the channel count.
+fn read_f64_wav(path: &str) -> std::io::Result<(Vec<f32>, u16, u32)> {
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+    let u16_at = |b: &[u8], i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+    let u32_at = |b: &[u8], i: usize| u32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF/WAVE file"));
+    }
+
+    let mut format_info = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = u32_at(&bytes, pos + 4) as usize;
+        let body = &bytes[pos + 8..(pos + 8 + len).min(bytes.len())];
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut format = u16_at(body, 0);
+                if format == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                    // The sub-format GUID starts with the actual format tag
+                    format = u16_at(body, 24);
+                }
+                if format != WAVE_FORMAT_IEEE_FLOAT || u16_at(body, 14) != 64 {
+                    return Err(invalid("not a 64-bit float WAV"));
+                }
+                format_info = Some((u16_at(body, 2), u32_at(body, 4)));
+            }
+            b"data" => {
+                let (channels, rate) =
+                    format_info.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                let samples = body
+                    .chunks_exact(8)
+                    .map(|b| f64::from_le_bytes(b.try_into().unwrap_or_default()) as f32)
+                    .collect();
+                return Ok((samples, channels, rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        pos += 8 + len + (len & 1);
+    }
+
+    Err(invalid("no data chunk"))
+}
+
+/// Average interleaved frames of `channels` samples into mono. Works for any channel
+/// count (stereo, 5.1, ...); a truncated final frame is averaged over what's present.
+fn downmix(samples: Vec<f32>, channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples;
+    }
+
+    samples
+        .chunks(channels as usize)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+fn dbfs_to_amplitude(dbfs: f32) -> f32 {
+    10f32.powf(dbfs / 20.0)
+}
+
+/// Scale samples towards the target RMS level, capping the gain so the peak stays
+/// below the ceiling. Silent buffers are left untouched.
+fn normalize(samples: &mut [f32]) {
+    let peak = samples.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+    if peak == 0.0 {
+        return;
+    }
+
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum_sq / samples.len() as f64).sqrt() as f32;
+
+    let gain = (dbfs_to_amplitude(NORMALIZE_TARGET_RMS_DBFS) / rms)
+        .min(dbfs_to_amplitude(NORMALIZE_PEAK_CEILING_DBFS) / peak);
+
+    for s in samples.iter_mut() {
+        *s = (*s * gain).clamp(-1.0, 1.0);
+    }
+}
+
+/// Average the probability of a segment's text tokens, skipping special tokens
+/// (timestamps, end-of-text, language tags), whose ids start at `token_eot`.
+fn segment_confidence(seg: &WhisperSegment<'_>, token_eot: WhisperTokenId) -> Option<f32> {
+    let probs: Vec<f32> = (0..seg.n_tokens())
+        .filter_map(|i| seg.get_token(i))
+        .filter(|tok| tok.token_id() < token_eot)
+        .map(|tok| tok.token_probability())
+        .collect();
+
+    if probs.is_empty() {
+        None
     } else {
-        samples
-    };
+        Some(probs.iter().sum::<f32>() / probs.len() as f32)
+    }
+}
+
+/// Load a ggml model once so it can be shared by several transcriptions.
+fn load_model(opts: &TranscribeOptions) -> Result<WhisperContext, TranscribeError> {
+    let model = resolve_model_path(opts.model_path, opts.model_dir)?;
+    Ok(WhisperContext::new_with_params(
+        &model,
+        WhisperContextParameters::default(),
+    )?)
+}
+
+/// Whisper's search and fallback settings. The default is beam search of width 5 with
+/// whisper.cpp's own thresholds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decoding {
+    /// Beam width; 1 decodes greedily.
+    pub beam_size: u8,
+    /// Re-decode a segment at a higher temperature when its token entropy is below
+    /// this (low entropy means repetition).
+    pub entropy_threshold: f32,
+    /// Re-decode a segment when its average token log-probability is below this.
+    pub logprob_threshold: f32,
+    /// Treat a window as silence when whisper's no-speech probability is above this.
+    pub no_speech_threshold: f32,
+    /// Suppress non-speech tokens such as `[Music]` or `(laughs)` while decoding, so
+    /// they never make it into the text.
+    pub suppress_non_speech: bool,
+    /// Keep whisper from starting a segment with a blank token. On by default, as in
+    /// whisper.cpp.
+    pub suppress_blank: bool,
+    /// Don't prompt each window with the previous text, so a repetition loop can't
+    /// carry over into the next one.
+    pub no_context: bool,
+}
+
+impl Default for Decoding {
+    fn default() -> Self {
+        Self {
+            beam_size: 5,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+            no_speech_threshold: 0.6,
+            suppress_non_speech: false,
+            suppress_blank: true,
+            no_context: false,
+        }
+    }
+}
 
-    Ok(mono)
+/// Named bundles of `Decoding` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Preset {
+    /// Wider beam (8); slower, fewer misheard words.
+    Accurate,
+    /// Greedy decoding; several times faster, a little less accurate.
+    Fast,
+    /// Fights hallucinations: stricter fallback thresholds, silence detected sooner,
+    /// non-speech tokens suppressed, no context carried between windows.
+    Clean,
+}
+
+impl Preset {
+    pub fn decoding(self) -> Decoding {
+        let default = Decoding::default();
+        match self {
+            Preset::Accurate => Decoding {
+                beam_size: 8,
+                ..default
+            },
+            Preset::Fast => Decoding {
+                beam_size: 1,
+                ..default
+            },
+            Preset::Clean => Decoding {
+                entropy_threshold: 2.8,
+                logprob_threshold: -0.8,
+                no_speech_threshold: 0.5,
+                suppress_non_speech: true,
+                no_context: true,
+                ..default
+            },
+        }
+    }
+}
+
+/// Decoding options shared by all transcription entry points.
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions<'a> {
+    /// Language code (e.g. "en", "it", "ar"). `None` for auto-detection.
+    pub language: Option<&'a str>,
+    /// Path to a ggml model file. `None` uses `$SAWT_MODEL`, then
+    /// `models/whisper-large-v3-turbo.bin`.
+    pub model_path: Option<&'a str>,
+    /// Directory bare model names are resolved in. `None` uses `$SAWT_MODEL_DIR`,
+    /// then `models/`.
+    pub model_dir: Option<&'a str>,
+    /// Raise quiet recordings to a consistent level before decoding.
+    pub normalize: bool,
+    /// When auto-detecting, pick the language from only the first N seconds and
+    /// keep it fixed for the whole file. `None` lets whisper decide over the file.
+    pub detect_window: Option<f64>,
+    /// Seconds added to every segment's start/end, e.g. where a clipped download
+    /// begins in the original video, so timestamps and `?t=` links line up.
+    pub offset_seconds: f64,
+    /// Cap segments at roughly this many characters, splitting on word boundaries.
+    /// `None` keeps whisper's own segmentation.
+    pub max_segment_chars: Option<usize>,
+    /// Skip whisper's timestamp tokens when only the text matters (e.g. for search).
+    /// Segments then span whole decoding windows (about 30 seconds each), so their
+    /// times are too coarse for subtitles or deep links. Ignores `max_segment_chars`.
+    pub no_timestamps: bool,
+    /// Drop segments shorter than this many seconds, e.g. the 0.2s noise fragments
+    /// whisper sometimes emits. Applies to streamed segments too.
+    pub min_segment_duration: Option<f64>,
+    /// Start decoding this many seconds into the file instead of at the beginning.
+    /// Timestamps stay relative to the start of the file.
+    pub from_seconds: f64,
+    /// Stop after decoding this many seconds. `None` runs to the end of the file.
+    pub duration_seconds: Option<f64>,
+    /// Beam width and thresholds, usually from a `Preset`.
+    pub decoding: Decoding,
+    /// When auto-detecting without a `detect_window`, also score the first 30 seconds
+    /// so `Transcript::language_candidates` is filled. Costs one extra encoder pass.
+    pub score_languages: bool,
+}
+
+/// One language whisper considered during detection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageCandidate {
+    /// Language code, e.g. "en".
+    pub code: String,
+    /// Detection probability between 0 and 1.
+    pub probability: f32,
+}
+
+/// Result of transcribing one file.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub segments: Vec<Segment>,
+    /// Language code used for decoding: the requested one, or the detected one.
+    pub language: Option<String>,
+    /// The top detection scores, best first. Empty when the language was given, or
+    /// when whisper detected it during decoding without `score_languages`.
+    pub language_candidates: Vec<LanguageCandidate>,
+    /// Set when whisper failed partway and `segments` is only what was decoded before
+    /// the error.
+    pub warning: Option<String>,
+    /// Set when the input is likely to transcribe badly, e.g. a WAV that isn't 16 kHz
+    /// (whisper reads it at the wrong speed).
+    pub input_warning: Option<String>,
+    /// Seconds of audio decoded (the `from_seconds`/`duration_seconds` window).
+    pub audio_seconds: f64,
+    /// Wall-clock time from reading the WAV to the finished transcript; excludes
+    /// loading the model.
+    pub elapsed: Duration,
+}
+
+impl Transcript {
+    /// Whether whisper produced any non-blank text.
+    pub fn has_speech(&self) -> bool {
+        self.segments.iter().any(|s| !s.text.trim().is_empty())
+    }
+
+    /// Processing time per second of audio (below 1.0 is faster than real time).
+    /// `None` for empty audio.
+    pub fn real_time_factor(&self) -> Option<f64> {
+        (self.audio_seconds > 0.0).then(|| self.elapsed.as_secs_f64() / self.audio_seconds)
+    }
 }
 
 /// Transcribe a WAV file using Whisper.
 ///
-/// - `file`: path to the WAV file (must be 16kHz or will be interpreted as-is by whisper.cpp)
-/// - `language`: optional language code (e.g. "en", "it", "ar"). `None` for auto-detection.
-/// - `model_path`: optional path to a ggml model file. Defaults to `models/ggml-large-v3.bin`.
-pub fn transcribe(
+/// `file` must be 16kHz mono, or it will be interpreted as-is by whisper.cpp.
+pub fn transcribe(file: &str, opts: &TranscribeOptions) -> Result<Transcript, TranscribeError> {
+    if !Path::new(file).exists() {
+        return Err(TranscribeError::FileNotFound(file.to_string()));
+    }
+
+    let ctx = load_model(opts)?;
+    transcribe_with(&ctx, file, opts)
+}
+
+/// Transcribe several WAV files with a single loaded model.
+///
+/// Up to `jobs` files (clamped to `1..=MAX_JOBS`) are decoded concurrently, each on
+/// its own whisper state. Results are returned in input order; a failing file does
+/// not abort the others. Only model loading fails the whole batch.
+pub fn transcribe_many(
+    files: &[String],
+    opts: &TranscribeOptions,
+    jobs: usize,
+) -> Result<Vec<Result<Transcript, TranscribeError>>, TranscribeError> {
+    Ok(Model::load(opts)?.transcribe_many(files, opts, jobs))
+}
+
+/// A whisper model loaded once for several `transcribe_many` calls, e.g. to work
+/// through a long list of files in batches without reloading it for each.
+pub struct Model {
+    ctx: WhisperContext,
+}
+
+impl Model {
+    /// Load the model `opts` selects (`model_path`, `model_dir`).
+    pub fn load(opts: &TranscribeOptions) -> Result<Self, TranscribeError> {
+        Ok(Self { ctx: load_model(opts)? })
+    }
+
+    /// Like the free `transcribe_many`, with this model.
+    pub fn transcribe_many(
+        &self,
+        files: &[String],
+        opts: &TranscribeOptions,
+        jobs: usize,
+    ) -> Vec<Result<Transcript, TranscribeError>> {
+        transcribe_many_with(&self.ctx, files, opts, jobs)
+    }
+}
+
+fn transcribe_many_with(
+    ctx: &WhisperContext,
+    files: &[String],
+    opts: &TranscribeOptions,
+    jobs: usize,
+) -> Vec<Result<Transcript, TranscribeError>> {
+    let jobs = jobs.clamp(1, MAX_JOBS).min(files.len().max(1));
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, Result<Transcript, TranscribeError>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(file) = files.get(i) else { break };
+                        done.push((i, transcribe_with(ctx, file, opts)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("transcription worker panicked"))
+            .collect()
+    });
+
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// Run whisper on one file using an already-loaded model.
+fn transcribe_with(
+    ctx: &WhisperContext,
+    file: &str,
+    opts: &TranscribeOptions,
+) -> Result<Transcript, TranscribeError> {
+    let started = Instant::now();
+    let (samples, input_warning) = load_samples(file, opts.normalize)?;
+    let (language, candidates) = resolve_language(ctx, &samples, opts)?;
+
+    let mut state = ctx.create_state()?;
+    let decoded = state.full(full_params(language.as_deref(), opts), &samples);
+
+    let mut transcript = finish_transcript(ctx, &state, decoded, language, opts.offset_seconds)?;
+    drop_short_segments(&mut transcript.segments, opts.min_segment_duration);
+    transcript.language_candidates = candidates;
+    transcript.input_warning = input_warning;
+    transcript.audio_seconds = decoded_seconds(samples.len(), opts);
+    transcript.elapsed = started.elapsed();
+    Ok(transcript)
+}
+
+/// Length of the window whisper decodes out of `n_samples` of audio.
+fn decoded_seconds(n_samples: usize, opts: &TranscribeOptions) -> f64 {
+    let total = n_samples as f64 / SAMPLE_RATE as f64;
+    let remaining = (total - opts.from_seconds.max(0.0)).max(0.0);
+    opts.duration_seconds
+        .map_or(remaining, |d| d.max(0.0).min(remaining))
+}
+
+/// Transcribe a WAV file, calling `on_segment` as soon as whisper finishes each
+/// segment instead of waiting for the whole file.
+///
+/// Streamed segments carry no `confidence` (the callback exposes no token data);
+/// the returned transcript is the complete batch result, identical to `transcribe`.
+pub fn transcribe_streaming<F>(
     file: &str,
-    language: Option<&str>,
-    model_path: Option<&str>,
-) -> Result<Vec<Segment>, TranscribeError> {
+    opts: &TranscribeOptions,
+    mut on_segment: F,
+) -> Result<Transcript, TranscribeError>
+where
+    F: FnMut(&Segment),
+{
     if !Path::new(file).exists() {
         return Err(TranscribeError::FileNotFound(file.to_string()));
     }
 
-    let model = resolve_model_path(model_path)?;
-    let ctx = WhisperContext::new_with_params(&model, WhisperContextParameters::default())?;
+    let ctx = load_model(opts)?;
+    let started = Instant::now();
+    let (samples, input_warning) = load_samples(file, opts.normalize)?;
+    let (language, candidates) = resolve_language(&ctx, &samples, opts)?;
     let mut state = ctx.create_state()?;
 
-    let mut params = FullParams::new(SamplingStrategy::BeamSearch {
-        beam_size: 5,
-        patience: -1.0,
+    // whisper's callback must be 'static, so it forwards segments over a channel
+    // and the caller's closure runs here while decoding continues on a worker.
+    let (tx, rx) = mpsc::channel();
+    let offset = opts.offset_seconds;
+    let min_duration = opts.min_segment_duration.unwrap_or(0.0);
+    let mut params = full_params(language.as_deref(), opts);
+    params.set_segment_callback_safe_lossy(move |data: SegmentCallbackData| {
+        if ((data.end_timestamp - data.start_timestamp) as f64 / 100.0) < min_duration {
+            return;
+        }
+        let _ = tx.send(Segment::from_whisper(
+            offset + data.start_timestamp as f64 / 100.0,
+            offset + data.end_timestamp as f64 / 100.0,
+            &data.text,
+            None,
+        ));
     });
+
+    let decoded = thread::scope(|scope| {
+        let worker = scope.spawn(|| state.full(params, &samples));
+        // whisper-rs never drops the callback, so the channel never disconnects;
+        // stop once the worker is done and drain what's left.
+        loop {
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(seg) => on_segment(&seg),
+                Err(RecvTimeoutError::Timeout) if worker.is_finished() => break,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        rx.try_iter().for_each(|seg| on_segment(&seg));
+        worker.join().expect("whisper worker panicked")
+    });
+
+    let mut transcript = finish_transcript(&ctx, &state, decoded, language, opts.offset_seconds)?;
+    drop_short_segments(&mut transcript.segments, opts.min_segment_duration);
+    transcript.language_candidates = candidates;
+    transcript.input_warning = input_warning;
+    transcript.audio_seconds = decoded_seconds(samples.len(), opts);
+    transcript.elapsed = started.elapsed();
+    Ok(transcript)
+}
+
+/// Remove segments lasting less than `min_duration` seconds.
+fn drop_short_segments(segments: &mut Vec<Segment>, min_duration: Option<f64>) {
+    if let Some(min) = min_duration {
+        segments.retain(|s| s.end - s.start >= min);
+    }
+}
+
+/// Decide the decoding language up front: the requested one, or (with a detection
+/// window) whatever whisper detects on the first `detect_window` seconds, counted
+/// from `from_seconds`. Also returns the top candidates whenever detection ran here;
+/// with `score_languages` and no window the language is still left to whisper.
+fn resolve_language(
+    ctx: &WhisperContext,
+    samples: &[f32],
+    opts: &TranscribeOptions,
+) -> Result<(Option<String>, Vec<LanguageCandidate>), TranscribeError> {
+    if let Some(lang) = opts.language {
+        return Ok((Some(lang.to_string()), Vec::new()));
+    }
+    let window = match opts.detect_window {
+        Some(window) => window,
+        None if opts.score_languages => SCORE_WINDOW_SECONDS,
+        None => return Ok((None, Vec::new())),
+    };
+
+    let start = ((opts.from_seconds.max(0.0) * SAMPLE_RATE as f64) as usize).min(samples.len());
+    let n = (window.max(0.0) * SAMPLE_RATE as f64) as usize;
+    let end = start.saturating_add(n).min(samples.len());
+    let threads = thread::available_parallelism().map_or(1, |n| n.get().min(4));
+
+    let mut state = ctx.create_state()?;
+    state.pcm_to_mel(&samples[start..end], threads)?;
+    let (lang_id, probs) = state.lang_detect(0, threads)?;
+
+    let candidates = top_languages(&probs, LANGUAGE_CANDIDATES);
+    let language = match opts.detect_window {
+        Some(_) => whisper_rs::get_lang_str(lang_id).map(str::to_string),
+        None => None,
+    };
+    Ok((language, candidates))
+}
+
+/// The `n` most probable languages from whisper's per-language scores (indexed by
+/// language id), best first.
+fn top_languages(probs: &[f32], n: usize) -> Vec<LanguageCandidate> {
+    let mut ranked: Vec<(usize, f32)> = probs.iter().copied().enumerate().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+        .into_iter()
+        .filter_map(|(id, probability)| {
+            let code = whisper_rs::get_lang_str(id.try_into().ok()?)?;
+            Some(LanguageCandidate { code: code.to_string(), probability })
+        })
+        .take(n)
+        .collect()
+}
+
+fn load_samples(
+    file: &str,
+    normalize_audio: bool,
+) -> Result<(Vec<f32>, Option<String>), TranscribeError> {
+    if !Path::new(file).exists() {
+        return Err(TranscribeError::FileNotFound(file.to_string()));
+    }
+
+    let (mut samples, warning) = read_wav(file)?;
+    if normalize_audio {
+        normalize(&mut samples);
+    }
+    Ok((samples, warning))
+}
+
+fn full_params<'a>(language: Option<&'a str>, opts: &TranscribeOptions) -> FullParams<'a, 'a> {
+    let decoding = opts.decoding;
+    let strategy = if decoding.beam_size <= 1 {
+        SamplingStrategy::Greedy { best_of: 1 }
+    } else {
+        SamplingStrategy::BeamSearch {
+            beam_size: decoding.beam_size.into(),
+            patience: -1.0,
+        }
+    };
+    let mut params = FullParams::new(strategy);
+    params.set_entropy_thold(decoding.entropy_threshold);
+    params.set_logprob_thold(decoding.logprob_threshold);
+    params.set_no_speech_thold(decoding.no_speech_threshold);
+    params.set_suppress_nst(decoding.suppress_non_speech);
+    params.set_suppress_blank(decoding.suppress_blank);
+    params.set_no_context(decoding.no_context);
     params.set_language(language);
     params.set_print_progress(false);
     params.set_print_realtime(false);
     params.set_print_timestamps(false);
+    params.set_no_timestamps(opts.no_timestamps);
+    if let Some(max_chars) = opts.max_segment_chars.filter(|_| !opts.no_timestamps) {
+        // whisper.cpp only wraps segments when token timestamps are enabled
+        params.set_token_timestamps(true);
+        params.set_max_len(max_chars.try_into().unwrap_or(i32::MAX));
+        params.set_split_on_word(true);
+    }
+    // whisper.cpp reports timestamps from the file start, so no offset adjustment is needed
+    if opts.from_seconds > 0.0 {
+        params.set_offset_ms(to_millis(opts.from_seconds));
+    }
+    if let Some(duration) = opts.duration_seconds {
+        params.set_duration_ms(to_millis(duration));
+    }
+    params
+}
+
+fn to_millis(seconds: f64) -> std::ffi::c_int {
+    (seconds.max(0.0) * 1000.0).round().min(std::ffi::c_int::MAX as f64) as std::ffi::c_int
+}
 
-    let samples = read_wav(file)?;
-    state.full(params, &samples)?;
+/// Turn the outcome of `full` into a transcript. If decoding failed after some segments
+/// were already produced (e.g. on a corrupt tail), keep them and attach a warning
+/// instead of discarding hours of work.
+fn finish_transcript(
+    ctx: &WhisperContext,
+    state: &WhisperState,
+    decoded: Result<std::ffi::c_int, whisper_rs::WhisperError>,
+    language: Option<String>,
+    offset: f64,
+) -> Result<Transcript, TranscribeError> {
+    match decoded {
+        Ok(_) => Ok(collect_transcript(ctx, state, language, offset)),
+        Err(e) if state.full_n_segments() > 0 => {
+            let mut transcript = collect_transcript(ctx, state, language, offset);
+            let last = transcript.segments.last().map_or(0.0, |s| s.end);
+            transcript.warning = Some(format!(
+                "whisper failed after {} segment(s), transcript ends at {}: {e}",
+                transcript.segments.len(),
+                format_clock(last)
+            ));
+            Ok(transcript)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
 
-    let segments: Vec<Segment> = state
+/// Read segments back from a finished state, shifted by `offset` seconds. Without a
+/// preset `language`, the one whisper auto-detected during `full` is reported.
+fn collect_transcript(
+    ctx: &WhisperContext,
+    state: &WhisperState,
+    language: Option<String>,
+    offset: f64,
+) -> Transcript {
+    let token_eot = ctx.token_eot();
+    let segments = state
         .as_iter()
-        .map(|seg| Segment {
-            start: seg.start_timestamp() as f64 / 100.0,
-            end: seg.end_timestamp() as f64 / 100.0,
-            text: seg
-                .to_str_lossy()
-                .unwrap_or_default()
-                .trim()
-                .to_string(),
+        .map(|seg| {
+            Segment::from_whisper(
+                offset + seg.start_timestamp() as f64 / 100.0,
+                offset + seg.end_timestamp() as f64 / 100.0,
+                &seg.to_str_lossy().unwrap_or_default(),
+                segment_confidence(&seg, token_eot),
+            )
         })
         .collect();
 
-    Ok(segments)
+    let language = language.or_else(|| {
+        whisper_rs::get_lang_str(state.full_lang_id_from_state()).map(str::to_string)
+    });
+
+    Transcript {
+        segments,
+        language,
+        language_candidates: Vec::new(),
+        warning: None,
+        input_warning: None,
+        audio_seconds: 0.0,
+        elapsed: Duration::ZERO,
+    }
 }
 
 /// Format segments as a console table.
@@ -132,8 +901,8 @@ pub fn format_table(segments: &[Segment]) -> comfy_table::Table {
     for (i, seg) in segments.iter().enumerate() {
         table.add_row([
             (i + 1).to_string(),
-            format_timestamp(seg.start),
-            format_timestamp(seg.end),
+            format_clock(seg.start),
+            format_clock(seg.end),
             seg.text.clone(),
         ]);
     }
@@ -141,8 +910,25 @@ pub fn format_table(segments: &[Segment]) -> comfy_table::Table {
     table
 }
 
-fn format_timestamp(seconds: f64) -> String {
-    let mins = (seconds / 60.0) as u32;
-    let secs = seconds % 60.0;
-    format!("{mins:02}:{secs:05.2}")
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmix_averages_four_channels() {
+        // Two full frames and a truncated one with two of its four samples
+        let samples = vec![1.0, 0.0, 0.5, -0.5, 0.4, 0.4, 0.4, 0.4, 1.0, 0.0];
+        assert_eq!(downmix(samples, 4), vec![0.25, 0.4, 0.5]);
+    }
+
+    #[test]
+    fn raw_text_is_kept_only_when_cleaning_changed_it() {
+        let cleaned = Segment::from_whisper(0.0, 1.0, " Hello there. ", None);
+        assert_eq!(cleaned.text, "Hello there.");
+        assert_eq!(cleaned.raw_text.as_deref(), Some(" Hello there. "));
+
+        let untouched = Segment::from_whisper(1.0, 2.0, "Hello there.", None);
+        assert_eq!(untouched.text, "Hello there.");
+        assert_eq!(untouched.raw_text, None);
+    }
 }